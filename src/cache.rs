@@ -0,0 +1,233 @@
+use core::ptr;
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use clap::ValueEnum;
+
+use crate::preflight::{allowed_cpus, current_cpu, pin_to_cpu};
+
+const CACHE_LINE: usize = 64;
+
+/// Bigger than the LLC of any machine we bench on
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const EVICTION_SIZE: usize = 128 * 1024 * 1024;
+
+/// Where the maps are before each measured round
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CacheState {
+    /// Leave the maps in the caches of the benchmark core
+    Hot,
+    /// Flush the maps out of all cache levels
+    Cold,
+    /// Touch the maps from another thread, like a target writing the map on another core
+    Llc,
+}
+
+pub struct CacheControl {
+    pub state: CacheState,
+    llc: Option<LlcHelper>,
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    eviction: Vec<u8>,
+}
+
+impl CacheControl {
+    pub fn new(state: CacheState, pinned: Option<usize>) -> Self {
+        Self {
+            state,
+            llc: (state == CacheState::Llc).then(|| LlcHelper::new(pinned)),
+            #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+            eviction: if state == CacheState::Cold {
+                vec![0; EVICTION_SIZE]
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    /// Bring `bufs` into the cache state, called between rounds
+    pub fn prepare(&mut self, bufs: &mut [&mut [u8]]) {
+        match self.state {
+            CacheState::Hot => {}
+            CacheState::Cold => self.flush(bufs.iter().map(|buf| &**buf)),
+            CacheState::Llc => self.llc.as_mut().unwrap().touch(bufs),
+        }
+    }
+
+    /// Bring `buf`, which other threads read at the same time, into the cache state. Since it
+    /// can't be written, `llc` flushes it and has the helper read it instead, which also leaves
+    /// it out of the caches of the benchmark core.
+    pub fn prepare_shared(&mut self, buf: &[u8]) {
+        match self.state {
+            CacheState::Hot => {}
+            CacheState::Cold => self.flush([buf]),
+            CacheState::Llc => {
+                self.flush([buf]);
+                self.llc.as_mut().unwrap().read(buf);
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn flush<'a>(&mut self, bufs: impl IntoIterator<Item = &'a [u8]>) {
+        use core::arch::x86_64::{_mm_clflush, _mm_mfence};
+        for buf in bufs {
+            for offset in line_offsets(buf) {
+                unsafe { _mm_clflush(buf.as_ptr().add(offset)) };
+            }
+        }
+        unsafe { _mm_mfence() };
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn flush<'a>(&mut self, bufs: impl IntoIterator<Item = &'a [u8]>) {
+        for buf in bufs {
+            for offset in line_offsets(buf) {
+                unsafe { core::arch::asm!("dc civac, {}", in(reg) buf.as_ptr().add(offset)) };
+            }
+        }
+        unsafe { core::arch::asm!("dsb ish") };
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn flush<'a>(&mut self, _bufs: impl IntoIterator<Item = &'a [u8]>) {
+        // no portable flush instruction, evict the maps by touching a buffer larger than the LLC
+        touch(&mut self.eviction);
+    }
+}
+
+/// Buffers for the `llc` helper to touch, or only read if `shared`, which stay borrowed until it
+/// is done with them
+struct Bufs {
+    bufs: Vec<(*mut u8, usize)>,
+    shared: bool,
+}
+
+unsafe impl Send for Bufs {}
+
+/// One thread for all rounds, pinned to another cpu than the benchmark
+struct LlcHelper {
+    jobs: Option<Sender<Bufs>>,
+    done: Receiver<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl LlcHelper {
+    /// Panics if there is no cpu besides the benchmark cpu in the affinity mask, since touching
+    /// the maps from the same core would leave them in its caches, the same as `hot`
+    fn new(pinned: Option<usize>) -> Self {
+        let bench = pinned.or_else(current_cpu);
+        // from the end, so helpers of threads pinned to consecutive cpus from a low `--cpu`
+        // rarely land on another benchmark thread
+        let cpu = allowed_cpus()
+            .iter()
+            .rev()
+            .copied()
+            .find(|cpu| Some(*cpu) != bench);
+        if cpu.is_none() {
+            if cfg!(target_os = "linux") {
+                panic!(
+                    "-c llc needs a cpu besides cpu{} in the affinity mask, allow more cpus \
+                     with taskset and pick the benchmark one with --cpu",
+                    bench.map_or("?".to_string(), |cpu| cpu.to_string())
+                );
+            }
+            println!("Warning: cannot pin the llc helper, it may share the benchmark core");
+        }
+
+        let (jobs, inbox) = mpsc::channel::<Bufs>();
+        let (outbox, done) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            if let Some((cpu, Err(e))) = cpu.map(|cpu| (cpu, pin_to_cpu(cpu))) {
+                println!("Warning: failed to pin the llc helper to cpu{}: {}", cpu, e);
+                return;
+            }
+            outbox.send(()).unwrap();
+            for bufs in inbox {
+                for (ptr, len) in bufs.bufs {
+                    if bufs.shared {
+                        read(unsafe { core::slice::from_raw_parts(ptr, len) });
+                    } else {
+                        touch(unsafe { core::slice::from_raw_parts_mut(ptr, len) });
+                    }
+                }
+                if outbox.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        done.recv().expect("the llc helper did not start");
+
+        Self {
+            jobs: Some(jobs),
+            done,
+            handle: Some(handle),
+        }
+    }
+
+    fn touch(&mut self, bufs: &mut [&mut [u8]]) {
+        self.run(Bufs {
+            bufs: bufs
+                .iter_mut()
+                .map(|buf| (buf.as_mut_ptr(), buf.len()))
+                .collect(),
+            shared: false,
+        });
+    }
+
+    fn read(&mut self, buf: &[u8]) {
+        self.run(Bufs {
+            bufs: vec![(buf.as_ptr().cast_mut(), buf.len())],
+            shared: true,
+        });
+    }
+
+    fn run(&mut self, bufs: Bufs) {
+        self.jobs.as_ref().unwrap().send(bufs).unwrap();
+        // `bufs` are borrowed until the helper is done
+        self.done.recv().expect("the llc helper died");
+    }
+}
+
+impl Drop for LlcHelper {
+    fn drop(&mut self) {
+        // closing the channel ends the helper
+        self.jobs.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// The offset of one byte of `buf` in each cache line it spans. `buf` need not start on a line,
+/// so `CACHE_LINE` chunks from its start would miss the last line it ends in.
+fn line_offsets(buf: &[u8]) -> impl Iterator<Item = usize> + use<> {
+    let start = buf.as_ptr() as usize;
+    let end = start + buf.len();
+    let first = if buf.is_empty() {
+        end
+    } else {
+        start & !(CACHE_LINE - 1)
+    };
+    (first..end)
+        .step_by(CACHE_LINE)
+        .map(move |line| line.max(start) - start)
+}
+
+/// Write back every cache line of `buf` unchanged, which moves the line to the current core
+fn touch(buf: &mut [u8]) {
+    for offset in line_offsets(buf) {
+        unsafe {
+            let p = buf.as_mut_ptr().add(offset);
+            ptr::write_volatile(p, ptr::read_volatile(p));
+        }
+    }
+}
+
+/// Read every cache line of `buf`, which brings the line into the caches of the current core
+fn read(buf: &[u8]) {
+    for offset in line_offsets(buf) {
+        unsafe { ptr::read_volatile(buf.as_ptr().add(offset)) };
+    }
+}
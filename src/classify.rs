@@ -0,0 +1,186 @@
+#[cfg(feature = "nightly")]
+use core::simd::{LaneCount, Simd, SupportedLaneCount, cmp::SimdPartialOrd};
+
+use wide::CmpEq;
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// Lower bound and class of each non-zero bucket in `COUNT_CLASS_LOOKUP`
+pub const COUNT_CLASS_RANGES: [(u8, u8); 8] = [
+    (1, 1),
+    (2, 2),
+    (3, 4),
+    (4, 8),
+    (8, 16),
+    (16, 32),
+    (32, 64),
+    (128, 128),
+];
+
+/// `COUNT_CLASS_LOOKUP` for every lane, with compares instead of a table
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn classify_simd<const N: usize>(items: Simd<u8, N>) -> Simd<u8, N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    classify_ranges_simd(items, &COUNT_CLASS_RANGES)
+}
+
+/// `COUNT_CLASS_LOOKUP` for every lane, with compares instead of a table
+#[inline]
+pub fn classify_wide256(items: wide::u8x32) -> wide::u8x32 {
+    classify_ranges_wide256(items, &COUNT_CLASS_RANGES)
+}
+
+/// Class of the last `(lower, class)` of `ranges` with `lower <= item` for every lane, 0 if none
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn classify_ranges_simd<const N: usize>(items: Simd<u8, N>, ranges: &[(u8, u8)]) -> Simd<u8, N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let mut out = Simd::splat(0);
+    for (lower, class) in ranges {
+        out = items
+            .simd_ge(Simd::splat(*lower))
+            .select(Simd::splat(*class), out);
+    }
+    out
+}
+
+/// Same as `classify_ranges_simd`
+#[inline]
+pub fn classify_ranges_wide256(items: wide::u8x32, ranges: &[(u8, u8)]) -> wide::u8x32 {
+    type VectorType = wide::u8x32;
+    let mut out = VectorType::ZERO;
+    for (lower, class) in ranges {
+        let ge = ge_wide256(items, VectorType::new([*lower; 32]));
+        out = ge.blend(VectorType::new([*class; 32]), out);
+    }
+    out
+}
+
+/// All ones in the lanes where `items >= bound`. There is no unsigned >= in wide, but
+/// `items >= bound` iff `max(items, bound) == items`.
+#[inline]
+pub(crate) fn ge_wide256(items: wide::u8x32, bound: wide::u8x32) -> wide::u8x32 {
+    items.max(bound).cmp_eq(items)
+}
+
+/// Hitcount buckets as ascending `(lower, class)` pairs: counts from `lower` up to the next lower
+/// bound become `class`, counts below the first bound become 0. Lookup tables and classify
+/// kernels are generated from the buckets.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BucketScheme {
+    ranges: Vec<(u8, u8)>,
+}
+
+#[cfg(feature = "alloc")]
+impl BucketScheme {
+    pub fn new(ranges: Vec<(u8, u8)>) -> Self {
+        assert!(
+            ranges.windows(2).all(|w| w[0].0 < w[1].0),
+            "bucket lower bounds must be ascending"
+        );
+        assert!(
+            ranges.first().is_none_or(|(lower, _)| *lower > 0),
+            "0 is always its own bucket"
+        );
+        Self { ranges }
+    }
+
+    /// AFL's buckets, 1, 2, 3, 4-7, 8-15, 16-31, 32-127 and 128+
+    pub fn afl() -> Self {
+        Self::new(COUNT_CLASS_RANGES.to_vec())
+    }
+
+    /// One bucket per power of two, 1, 2-3, 4-7, ... 128+
+    pub fn log2() -> Self {
+        Self::new((0..8).map(|bit| (1 << bit, 1 << bit)).collect())
+    }
+
+    /// Few buckets, 1-3, 4-31 and 32+
+    pub fn coarse() -> Self {
+        Self::new(vec![(1, 1), (4, 2), (32, 4)])
+    }
+
+    /// Only hit or not
+    pub fn binary() -> Self {
+        Self::new(vec![(1, 1)])
+    }
+
+    pub fn ranges(&self) -> &[(u8, u8)] {
+        &self.ranges
+    }
+
+    #[inline]
+    pub fn classify(&self, count: u8) -> u8 {
+        self.ranges
+            .iter()
+            .rev()
+            .find(|(lower, _)| count >= *lower)
+            .map_or(0, |(_, class)| *class)
+    }
+
+    /// The class of every count, like `COUNT_CLASS_LOOKUP`
+    pub fn lookup(&self) -> [u8; 256] {
+        core::array::from_fn(|count| self.classify(count as u8))
+    }
+
+    /// The classes of every pair of counts, like the table of `init_count_class_16`
+    pub fn lookup16(&self) -> Vec<u16> {
+        let lookup = self.lookup();
+        (0..=u16::MAX)
+            .map(|pair| {
+                let [lo, hi] = pair.to_ne_bytes();
+                u16::from_ne_bytes([lookup[lo as usize], lookup[hi as usize]])
+            })
+            .collect()
+    }
+
+    /// Classify `map` in place with `lookup`
+    pub fn classify_counts_lookup(lookup: &[u8; 256], map: &mut [u8]) {
+        for it in map.iter_mut() {
+            *it = lookup[*it as usize];
+        }
+    }
+
+    /// Classify `map` in place two entries at a time with `lookup16`
+    pub fn classify_counts_lookup16(lookup16: &[u16], map: &mut [u8]) {
+        let mut pairs = map.chunks_exact_mut(2);
+        for pair in pairs.by_ref() {
+            let classes = lookup16[u16::from_ne_bytes([pair[0], pair[1]]) as usize];
+            pair.copy_from_slice(&classes.to_ne_bytes());
+        }
+        for it in pairs.into_remainder() {
+            *it = lookup16[u16::from_ne_bytes([*it, 0]) as usize].to_ne_bytes()[0];
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    pub fn classify_counts_simd(&self, map: &mut [u8]) {
+        type VectorType = core::simd::u8x32;
+        let mut chunks = map.chunks_exact_mut(VectorType::LEN);
+        for chunk in chunks.by_ref() {
+            classify_ranges_simd(VectorType::from_slice(chunk), &self.ranges).copy_to_slice(chunk);
+        }
+        for it in chunks.into_remainder() {
+            *it = self.classify(*it);
+        }
+    }
+
+    pub fn classify_counts_wide256(&self, map: &mut [u8]) {
+        type VectorType = wide::u8x32;
+        let mut chunks = map.chunks_exact_mut(VectorType::LANES as usize);
+        for chunk in chunks.by_ref() {
+            let items = VectorType::new(chunk.as_ref().try_into().unwrap());
+            chunk.copy_from_slice(&classify_ranges_wide256(items, &self.ranges).to_array());
+        }
+        for it in chunks.into_remainder() {
+            *it = self.classify(*it);
+        }
+    }
+}
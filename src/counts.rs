@@ -1,9 +1,17 @@
+use core::ops::RangeInclusive;
+#[cfg(feature = "nightly")]
+use core::simd::{LaneCount, Simd, SupportedLaneCount, cmp::SimdPartialEq, num::SimdUint};
 use core::slice;
 
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use crate::classify::COUNT_CLASS_RANGES;
+
 use wide::CmpEq;
 
 /// Hitcounts class lookup
-static COUNT_CLASS_LOOKUP: [u8; 256] = [
+pub static COUNT_CLASS_LOOKUP: [u8; 256] = [
     0, 1, 2, 4, 8, 8, 8, 8, 16, 16, 16, 16, 16, 16, 16, 16, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32,
     32, 32, 32, 32, 32, 32, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
     64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
@@ -18,24 +26,24 @@ static COUNT_CLASS_LOOKUP: [u8; 256] = [
     128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
 ];
 
-/// Hitcounts class lookup for 16-byte values
-static mut COUNT_CLASS_LOOKUP_16: Vec<u16> = vec![];
+/// Hitcounts class lookup for 16-byte values, filled by `init_count_class_16`
+static mut COUNT_CLASS_LOOKUP_16: [u16; 65536] = [0; 65536];
 
 /// Initialize the 16-byte hitcounts map
 pub fn init_count_class_16() {
     // # Safety
     //
-    // Calling this from multiple threads may be racey and hence leak 65k mem or even create a broken lookup vec.
+    // Calling this from multiple threads may be racey and hence create a broken lookup table.
     // We can live with that.
     unsafe {
         let count_class_lookup_16 = &raw mut COUNT_CLASS_LOOKUP_16;
         let count_class_lookup_16 = &mut *count_class_lookup_16;
 
-        if !count_class_lookup_16.is_empty() {
+        // the last entry is filled last and never classified as 0
+        if count_class_lookup_16[0xffff] != 0 {
             return;
         }
 
-        *count_class_lookup_16 = vec![0; 65536];
         for i in 0..256 {
             for j in 0..256 {
                 count_class_lookup_16[(i << 8) + j] =
@@ -132,7 +140,7 @@ pub fn afl_simplify_trace_wide256(map: &mut [u8]) {
     for step in 0..steps {
         let i = step * bs;
         let buf: [u8; 32] = map[i..i+bs].try_into().unwrap();
-        let mp = VectorType::new(unsafe {std::mem::transmute::<[u8; 32], [i8; 32]>(buf)});
+        let mp = VectorType::new(unsafe {core::mem::transmute::<[u8; 32], [i8; 32]>(buf)});
 
         let mask = mp.cmp_eq(VectorType::ZERO);
         // let out = lhs.blend(rhs, mask);
@@ -149,3 +157,361 @@ pub fn afl_simplify_trace_wide256(map: &mut [u8]) {
         map[j] = if map[j] == 0 { 0x1 } else { 0x80 }
     }
 }
+
+#[cfg(feature = "nightly")]
+#[inline]
+fn afl_simplify_trace_simd<const N: usize>(map: &mut [u8])
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let zero = Simd::<u8, N>::splat(0);
+    let lhs = Simd::splat(0x1);
+    let rhs = Simd::splat(0x80);
+    let mut chunks = map.chunks_exact_mut(N);
+    for chunk in chunks.by_ref() {
+        let mp = Simd::<u8, N>::from_slice(chunk);
+        mp.simd_eq(zero).select(lhs, rhs).copy_to_slice(chunk);
+    }
+
+    for it in chunks.into_remainder() {
+        *it = if *it == 0 { 0x1 } else { 0x80 }
+    }
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_simplify_trace_simd128(map: &mut [u8]) {
+    afl_simplify_trace_simd::<16>(map)
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_simplify_trace_simd256(map: &mut [u8]) {
+    afl_simplify_trace_simd::<32>(map)
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_simplify_trace_simd512(map: &mut [u8]) {
+    afl_simplify_trace_simd::<64>(map)
+}
+
+/// `afl_simplify_trace_naive` without the branch, in fixed chunks the compiler can vectorize
+pub fn afl_simplify_trace_autovec(map: &mut [u8]) {
+    let mut chunks = map.chunks_exact_mut(32);
+    for chunk in chunks.by_ref() {
+        for it in chunk.iter_mut() {
+            // 0x1 for 0, 0x1 + 0x7f for anything else
+            *it = 0x1 + u8::from(*it != 0) * 0x7f;
+        }
+    }
+    for it in chunks.into_remainder() {
+        *it = 0x1 + u8::from(*it != 0) * 0x7f;
+    }
+}
+
+/// `COUNT_CLASS_LOOKUP` as a chain of compares and selects instead of a table lookup, which the
+/// compiler can vectorize
+#[inline]
+fn classify_branchless(it: u8) -> u8 {
+    let mut out = 0;
+    for (lower, class) in COUNT_CLASS_RANGES {
+        out = if it >= lower { class } else { out };
+    }
+    out
+}
+
+pub fn afl_classify_counts_autovec(map: &mut [u8]) {
+    let mut chunks = map.chunks_exact_mut(32);
+    for chunk in chunks.by_ref() {
+        for it in chunk.iter_mut() {
+            *it = classify_branchless(*it);
+        }
+    }
+    for it in chunks.into_remainder() {
+        *it = classify_branchless(*it);
+    }
+}
+
+/// Class of a u16 counter: its highest set bit, so the buckets are 0, 1, 2-3, 4-7, ... 32768+
+#[inline]
+fn classify_u16(count: u16) -> u16 {
+    if count == 0 {
+        0
+    } else {
+        0x8000 >> count.leading_zeros()
+    }
+}
+
+/// Compressed class of a u16 counter: the number of its bucket, 0 for 0 up to 16 for 32768+.
+/// Ordered like the u16 classes, so the max reducer works the same on both.
+#[inline]
+fn classify_u16_to_u8(count: u16) -> u8 {
+    (u16::BITS - count.leading_zeros()) as u8
+}
+
+/// Classify a map of u16 counters in place
+pub fn afl_classify_counts_u16_naive(map: &mut [u16]) {
+    for it in map.iter_mut() {
+        *it = classify_u16(*it);
+    }
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_classify_counts_u16_simd(map: &mut [u16]) {
+    type VectorType = core::simd::u16x16;
+    let zero = VectorType::splat(0);
+    let top = VectorType::splat(0x8000);
+    let mut chunks = map.chunks_exact_mut(VectorType::LEN);
+    for chunk in chunks.by_ref() {
+        let items = VectorType::from_slice(chunk);
+        let out = items
+            .simd_eq(zero)
+            .select(zero, top >> items.leading_zeros());
+        out.copy_to_slice(chunk);
+    }
+    afl_classify_counts_u16_naive(chunks.into_remainder());
+}
+
+/// Set every bit below the highest set bit of each lane
+#[inline]
+fn smear_wide(x: wide::u16x16) -> wide::u16x16 {
+    let x = x | (x >> 1);
+    let x = x | (x >> 2);
+    let x = x | (x >> 4);
+    x | (x >> 8)
+}
+
+pub fn afl_classify_counts_u16_wide256(map: &mut [u16]) {
+    // wide has no leading_zeros, isolate the highest bit by smearing it down
+    type VectorType = wide::u16x16;
+    let mut chunks = map.chunks_exact_mut(16);
+    for chunk in chunks.by_ref() {
+        let x = smear_wide(VectorType::new(chunk.as_ref().try_into().unwrap()));
+        let highest: VectorType = x - (x >> 1);
+        chunk.copy_from_slice(&highest.to_array());
+    }
+    afl_classify_counts_u16_naive(chunks.into_remainder());
+}
+
+/// Classify a map of u16 counters into compressed u8 classes in `dst`, which has the same length
+pub fn afl_classify_counts_u16_to_u8_naive(src: &[u16], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    for (s, d) in src.iter().zip(dst.iter_mut()) {
+        *d = classify_u16_to_u8(*s);
+    }
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_classify_counts_u16_to_u8_simd(src: &[u16], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    type VectorType = core::simd::u16x16;
+    const BS: usize = VectorType::LEN;
+    let bits = VectorType::splat(u16::BITS as u16);
+    let body = src.len() - src.len() % BS;
+    for (s, d) in src[..body]
+        .chunks_exact(BS)
+        .zip(dst[..body].chunks_exact_mut(BS))
+    {
+        let items = VectorType::from_slice(s);
+        (bits - items.leading_zeros()).cast::<u8>().copy_to_slice(d);
+    }
+    afl_classify_counts_u16_to_u8_naive(&src[body..], &mut dst[body..]);
+}
+
+pub fn afl_classify_counts_u16_to_u8_wide256(src: &[u16], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    // the bucket number is the popcount of the smeared counter, counted with SWAR
+    type VectorType = wide::u16x16;
+    const BS: usize = 16;
+    let m1 = VectorType::new([0x5555; BS]);
+    let m2 = VectorType::new([0x3333; BS]);
+    let m4 = VectorType::new([0x0f0f; BS]);
+    let low = VectorType::new([0x001f; BS]);
+    let body = src.len() - src.len() % BS;
+    for (s, d) in src[..body]
+        .chunks_exact(BS)
+        .zip(dst[..body].chunks_exact_mut(BS))
+    {
+        let x = smear_wide(VectorType::new(s.try_into().unwrap()));
+        let x = x - ((x >> 1) & m1);
+        let x = (x & m2) + ((x >> 2) & m2);
+        let x = (x + (x >> 4)) & m4;
+        let x: VectorType = (x + (x >> 8)) & low;
+        for (d, x) in d.iter_mut().zip(x.to_array()) {
+            *d = x as u8;
+        }
+    }
+    afl_classify_counts_u16_to_u8_naive(&src[body..], &mut dst[body..]);
+}
+
+/// `afl_classify_counts_naive16` reading `src` and writing `dst`, which has the same length.
+/// Needs `init_count_class_16` too.
+pub fn afl_classify_counts_naive16_to(src: &[u8], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    let count_class_lookup_16 = &raw const COUNT_CLASS_LOOKUP_16;
+    let count_class_lookup_16 = unsafe { (*count_class_lookup_16).as_slice() };
+    let body = src.len() - src.len() % 2;
+
+    for (s, d) in src[..body]
+        .chunks_exact(2)
+        .zip(dst[..body].chunks_exact_mut(2))
+    {
+        let item = u16::from_ne_bytes([s[0], s[1]]);
+        d.copy_from_slice(&count_class_lookup_16[item as usize].to_ne_bytes());
+    }
+
+    if body < src.len() {
+        dst[body] = COUNT_CLASS_LOOKUP[src[body] as usize];
+    }
+}
+
+/// `afl_simplify_trace_naive` reading `src` and writing `dst`, which has the same length
+pub fn afl_simplify_trace_naive_to(src: &[u8], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    for (s, d) in src.iter().zip(dst.iter_mut()) {
+        *d = if *s == 0 { 0x1 } else { 0x80 };
+    }
+}
+
+pub fn afl_simplify_trace_wide128_to(src: &[u8], dst: &mut [u8]) {
+    type VectorType = wide::u8x16;
+    const BS: usize = VectorType::LANES as usize;
+    assert_eq!(src.len(), dst.len());
+    let body = src.len() - src.len() % BS;
+    let lhs = VectorType::new([0x1; BS]);
+    let rhs = VectorType::new([0x80; BS]);
+
+    for (s, d) in src[..body]
+        .chunks_exact(BS)
+        .zip(dst[..body].chunks_exact_mut(BS))
+    {
+        let mp = VectorType::new(s.try_into().unwrap());
+        d.copy_from_slice(mp.cmp_eq(VectorType::ZERO).blend(lhs, rhs).as_array_ref());
+    }
+
+    afl_simplify_trace_naive_to(&src[body..], &mut dst[body..]);
+}
+
+pub fn afl_simplify_trace_wide256_to(src: &[u8], dst: &mut [u8]) {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    assert_eq!(src.len(), dst.len());
+    let body = src.len() - src.len() % BS;
+    let lhs = VectorType::new([0x1; BS]);
+    let rhs = VectorType::new([0x80; BS]);
+
+    for (s, d) in src[..body]
+        .chunks_exact(BS)
+        .zip(dst[..body].chunks_exact_mut(BS))
+    {
+        let mp = VectorType::new(s.try_into().unwrap());
+        d.copy_from_slice(mp.cmp_eq(VectorType::ZERO).blend(lhs, rhs).as_array_ref());
+    }
+
+    afl_simplify_trace_naive_to(&src[body..], &mut dst[body..]);
+}
+
+#[cfg(feature = "nightly")]
+#[inline]
+fn afl_simplify_trace_simd_to<const N: usize>(src: &[u8], dst: &mut [u8])
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    assert_eq!(src.len(), dst.len());
+    let body = src.len() - src.len() % N;
+    let zero = Simd::<u8, N>::splat(0);
+    let lhs = Simd::splat(0x1);
+    let rhs = Simd::splat(0x80);
+
+    for (s, d) in src[..body]
+        .chunks_exact(N)
+        .zip(dst[..body].chunks_exact_mut(N))
+    {
+        let mp = Simd::<u8, N>::from_slice(s);
+        mp.simd_eq(zero).select(lhs, rhs).copy_to_slice(d);
+    }
+
+    afl_simplify_trace_naive_to(&src[body..], &mut dst[body..]);
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_simplify_trace_simd128_to(src: &[u8], dst: &mut [u8]) {
+    afl_simplify_trace_simd_to::<16>(src, dst)
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_simplify_trace_simd256_to(src: &[u8], dst: &mut [u8]) {
+    afl_simplify_trace_simd_to::<32>(src, dst)
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_simplify_trace_simd512_to(src: &[u8], dst: &mut [u8]) {
+    afl_simplify_trace_simd_to::<64>(src, dst)
+}
+
+pub fn afl_simplify_trace_autovec_to(src: &[u8], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    let body = src.len() - src.len() % 32;
+    for (s, d) in src[..body]
+        .chunks_exact(32)
+        .zip(dst[..body].chunks_exact_mut(32))
+    {
+        for (s, d) in s.iter().zip(d.iter_mut()) {
+            *d = 0x1 + u8::from(*s != 0) * 0x7f;
+        }
+    }
+    afl_simplify_trace_naive_to(&src[body..], &mut dst[body..]);
+}
+
+/// The inclusive range of counts `COUNT_CLASS_LOOKUP` maps to `class`, `None` if no count does
+pub fn afl_bucket_range(class: u8) -> Option<RangeInclusive<u8>> {
+    let first = COUNT_CLASS_LOOKUP.iter().position(|it| *it == class)?;
+    let last = COUNT_CLASS_LOOKUP.iter().rposition(|it| *it == class)?;
+    Some(first as u8..=last as u8)
+}
+
+/// Pack a map simplified by `afl_simplify_trace_*` into one bit per entry, set for the hit
+/// (0x80) entries. Bit `i % 64` of word `i / 64` is entry `i`, like `NoveltyBitset::per_entry`.
+#[cfg(feature = "alloc")]
+pub fn afl_simplified_to_bitset_naive(map: &[u8]) -> Vec<u64> {
+    let mut words = vec![0; map.len().div_ceil(64)];
+    for (i, it) in map.iter().enumerate() {
+        words[i / 64] |= u64::from(*it >> 7) << (i % 64);
+    }
+    words
+}
+
+#[cfg(all(feature = "nightly", feature = "alloc"))]
+pub fn afl_simplified_to_bitset_simd(map: &[u8]) -> Vec<u64> {
+    use core::simd::cmp::SimdPartialOrd;
+
+    type VectorType = core::simd::u8x64;
+    let top = VectorType::splat(0x80);
+    let mut words = Vec::with_capacity(map.len().div_ceil(64));
+    let mut chunks = map.chunks_exact(VectorType::LEN);
+    for chunk in chunks.by_ref() {
+        words.push(VectorType::from_slice(chunk).simd_ge(top).to_bitmask());
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        words.extend(afl_simplified_to_bitset_naive(rest));
+    }
+    words
+}
+
+#[cfg(feature = "alloc")]
+pub fn afl_simplified_to_bitset_wide256(map: &[u8]) -> Vec<u64> {
+    // move_mask gathers the top bit of every lane, which is set for the hit entries only
+    type VectorType = wide::u8x32;
+    let mut words = Vec::with_capacity(map.len().div_ceil(64));
+    let mut chunks = map.chunks_exact(64);
+    for chunk in chunks.by_ref() {
+        let (lo, hi) = chunk.split_at(32);
+        let lo = VectorType::new(lo.try_into().unwrap()).move_mask() as u32;
+        let hi = VectorType::new(hi.try_into().unwrap()).move_mask() as u32;
+        words.push(u64::from(lo) | (u64::from(hi) << 32));
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        words.extend(afl_simplified_to_bitset_naive(rest));
+    }
+    words
+}
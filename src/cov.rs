@@ -1,9 +1,7 @@
-use chrono::Utc;
-use clap::Parser;
-use itertools::Itertools;
-use rand::{RngCore, rngs::ThreadRng};
-use wide::CmpEq;
-use std::simd::cmp::SimdOrd;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "nightly")]
+use core::simd::cmp::SimdOrd;
 
 pub struct MaxReducer {}
 
@@ -37,16 +35,208 @@ where
     }
 }
 
+/// Where the `_into` kernels put the indices of novel entries, so a fuzzer can reuse the storage
+/// across executions instead of allocating a `Vec` per call
+pub trait Novelties {
+    /// Forget all novelties, but keep the storage. The `_into` kernels call it first, also
+    /// without `NV`
+    fn clear(&mut self);
+    fn push(&mut self, idx: usize);
+}
+
+#[cfg(feature = "alloc")]
+impl Novelties for Vec<usize> {
+    #[inline]
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+
+    #[inline]
+    fn push(&mut self, idx: usize) {
+        Vec::push(self, idx);
+    }
+}
+
+/// Novelties in a caller-provided buffer which never grows. Novelties beyond its capacity are
+/// dropped and flagged as overflow.
+pub struct FixedNovelties<'a> {
+    buf: &'a mut [usize],
+    len: usize,
+    overflowed: bool,
+}
+
+impl<'a> FixedNovelties<'a> {
+    pub fn new(buf: &'a mut [usize]) -> Self {
+        Self {
+            buf,
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many novelties fit
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Whether some novelties didn't fit since the last `clear`
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+}
+
+impl AsRef<[usize]> for FixedNovelties<'_> {
+    fn as_ref(&self) -> &[usize] {
+        &self.buf[..self.len]
+    }
+}
+
+impl Novelties for FixedNovelties<'_> {
+    #[inline]
+    fn clear(&mut self) {
+        self.len = 0;
+        self.overflowed = false;
+    }
+
+    #[inline]
+    fn push(&mut self, idx: usize) {
+        if let Some(slot) = self.buf.get_mut(self.len) {
+            *slot = idx;
+            self.len += 1;
+        } else {
+            self.overflowed = true;
+        }
+    }
+}
+
+/// Half the size of `usize` novelties, maps never exceed 2^32 entries
+#[cfg(feature = "alloc")]
+impl Novelties for Vec<u32> {
+    #[inline]
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+
+    #[inline]
+    fn push(&mut self, idx: usize) {
+        Vec::push(
+            self,
+            u32::try_from(idx).expect("map too large for u32 novelties"),
+        );
+    }
+}
+
+/// Widen `u32` novelties back to indices
+#[cfg(feature = "alloc")]
+pub fn novelties_to_usize(novelties: &[u32]) -> Vec<usize> {
+    novelties.iter().map(|idx| *idx as usize).collect()
+}
+
+/// Novelties as one bit per map entry, or per block of entries. Clearing costs a pass over the
+/// bitset, which is 1/8 of the map per entry.
+#[cfg(feature = "alloc")]
+pub struct NoveltyBitset {
+    words: Vec<u64>,
+    /// log2 of the entries per bit
+    shift: u32,
+}
+
+#[cfg(feature = "alloc")]
+impl NoveltyBitset {
+    /// One bit per entry of a map with `len` entries
+    pub fn per_entry(len: usize) -> Self {
+        Self::per_block(len, 1)
+    }
+
+    /// One bit per `block` entries, `block` being a power of two such as the lanes of a vector
+    pub fn per_block(len: usize, block: usize) -> Self {
+        assert!(block.is_power_of_two(), "block must be a power of two");
+        let bits = len.div_ceil(block);
+        Self {
+            words: vec![0; bits.div_ceil(64)],
+            shift: block.trailing_zeros(),
+        }
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn block_size(&self) -> usize {
+        1 << self.shift
+    }
+
+    /// Whether the entry, or the block containing it, is novel
+    pub fn contains(&self, idx: usize) -> bool {
+        let bit = idx >> self.shift;
+        self.words[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    /// First index of each novel entry or block, in order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        let shift = self.shift;
+        self.words.iter().enumerate().flat_map(move |(i, word)| {
+            let mut word = *word;
+            core::iter::from_fn(move || {
+                if word == 0 {
+                    None
+                } else {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some((i * 64 + bit) << shift)
+                }
+            })
+        })
+    }
+
+    pub fn to_indices(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Novelties for NoveltyBitset {
+    #[inline]
+    fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    #[inline]
+    fn push(&mut self, idx: usize) {
+        let bit = idx >> self.shift;
+        self.words[bit / 64] |= 1 << (bit % 64);
+    }
+}
+
+#[cfg(all(feature = "nightly", feature = "alloc"))]
 pub fn afl_nightly_simd<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Vec<usize>) {
-    type VectorType = core::simd::u8x16;
     let mut novelties = vec![];
+    let interesting = afl_nightly_simd_into::<NV, _>(hist, map, &mut novelties);
+    (interesting, novelties)
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_nightly_simd_into<const NV: bool, N: Novelties>(
+    hist: &[u8],
+    map: &[u8],
+    novelties: &mut N,
+) -> bool {
+    type VectorType = core::simd::u8x16;
     let mut interesting = false;
     let size = map.len();
     let steps = size / VectorType::LEN;
     let left = size % VectorType::LEN;
 
+    novelties.clear();
     if NV {
-        novelties.clear();
         for step in 0..steps {
             let i = step * VectorType::LEN;
             let history = VectorType::from_slice(&hist[i..]);
@@ -99,19 +289,29 @@ pub fn afl_nightly_simd<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Vec<u
         }
     }
 
-    (interesting, novelties)
+    interesting
 }
 
+#[cfg(feature = "alloc")]
 pub fn afl_stable_wide_128<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Vec<usize>) {
-    type VectorType = wide::u8x16;
     let mut novelties = vec![];
+    let interesting = afl_stable_wide_128_into::<NV, _>(hist, map, &mut novelties);
+    (interesting, novelties)
+}
+
+pub fn afl_stable_wide_128_into<const NV: bool, N: Novelties>(
+    hist: &[u8],
+    map: &[u8],
+    novelties: &mut N,
+) -> bool {
+    type VectorType = wide::u8x16;
     let mut interesting = false;
     let size = map.len();
     let steps = size / VectorType::LANES as usize;
     let left = size % VectorType::LANES as usize;
 
+    novelties.clear();
     if NV {
-        novelties.clear();
         for step in 0..steps {
             let i = step * VectorType::LANES as usize;
             let history =
@@ -166,21 +366,31 @@ pub fn afl_stable_wide_128<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Ve
         }
     }
 
-    (interesting, novelties)
+    interesting
 }
 
 
+#[cfg(feature = "alloc")]
 pub fn afl_stable_wide_256<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Vec<usize>) {
-    type VectorType = wide::u8x32;
     let mut novelties = vec![];
+    let interesting = afl_stable_wide_256_into::<NV, _>(hist, map, &mut novelties);
+    (interesting, novelties)
+}
+
+pub fn afl_stable_wide_256_into<const NV: bool, N: Novelties>(
+    hist: &[u8],
+    map: &[u8],
+    novelties: &mut N,
+) -> bool {
+    type VectorType = wide::u8x32;
     let mut interesting = false;
     const bs: usize =  VectorType::LANES as usize;
     let size = map.len();
     let steps = size / bs;
     let left = size % bs;
     
+    novelties.clear();
     if NV {
-        novelties.clear();
         for step in 0..steps {
             let i = step * bs;
             let buf: [u8; bs] = hist[i..i+bs].try_into().unwrap();
@@ -224,9 +434,9 @@ pub fn afl_stable_wide_256<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Ve
             let i = step * bs;
             let buf: [u8; bs] = hist[i..i+bs].try_into().unwrap();
             let history =
-                VectorType::new(unsafe {std::mem::transmute(buf)});
+                VectorType::new(unsafe {core::mem::transmute(buf)});
             let buf: [u8; bs] = map[i..i+bs].try_into().unwrap();
-            let items = VectorType::new(unsafe {std::mem::transmute(buf)});
+            let items = VectorType::new(unsafe {core::mem::transmute(buf)});
 
             if items.max(history) != history {
                 interesting = true;
@@ -247,17 +457,89 @@ pub fn afl_stable_wide_256<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Ve
         }
     }
 
+    interesting
+}
+
+#[cfg(feature = "alloc")]
+pub fn afl_autovec<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Vec<usize>) {
+    let mut novelties = vec![];
+    let interesting = afl_autovec_into::<NV, _>(hist, map, &mut novelties);
     (interesting, novelties)
 }
 
+/// Plain scalar code without explicit SIMD, written so the compiler can vectorize it: fixed
+/// `chunks_exact` blocks reduced without branches, only the block result is branched on
+pub fn afl_autovec_into<const NV: bool, N: Novelties>(
+    hist: &[u8],
+    map: &[u8],
+    novelties: &mut N,
+) -> bool {
+    const BS: usize = 32;
+    let mut interesting = false;
+    let size = map.len();
+    let body = size - size % BS;
+
+    novelties.clear();
+
+    for (i, (items, history)) in map[..body]
+        .chunks_exact(BS)
+        .zip(hist[..body].chunks_exact(BS))
+        .enumerate()
+    {
+        let novel = items
+            .iter()
+            .zip(history.iter())
+            .fold(0, |acc, (item, existing)| acc | u8::from(item > existing));
+        if novel != 0 {
+            interesting = true;
+            if !NV {
+                return true;
+            }
+            for (j, (item, existing)) in items.iter().zip(history.iter()).enumerate() {
+                if item > existing {
+                    novelties.push(i * BS + j);
+                }
+            }
+        }
+    }
+
+    for j in body..size {
+        if map[j] > hist[j] {
+            interesting = true;
+            if !NV {
+                return true;
+            }
+            novelties.push(j);
+        }
+    }
+
+    interesting
+}
+
+#[cfg(feature = "alloc")]
 pub fn afl_default_impl<const NV: bool, R, N>(hist: &[u8], map: &[u8]) -> (bool, Vec<usize>)
 where
     R: Reducer<u8>,
     N: IsNovel<u8>,
 {
     let mut novelties = vec![];
+    let interesting = afl_default_impl_into::<NV, R, N, _>(hist, map, &mut novelties);
+    (interesting, novelties)
+}
+
+pub fn afl_default_impl_into<const NV: bool, R, N, S>(
+    hist: &[u8],
+    map: &[u8],
+    novelties: &mut S,
+) -> bool
+where
+    R: Reducer<u8>,
+    N: IsNovel<u8>,
+    S: Novelties,
+{
     let mut interesting = false;
     let initial = 0;
+    novelties.clear();
     if NV {
         for (i, item) in map.iter().enumerate().filter(|(_, item)| **item != initial) {
             let existing = unsafe { *hist.get_unchecked(i) };
@@ -278,5 +560,5 @@ where
         }
     }
 
-    (interesting, novelties)
+    interesting
 }
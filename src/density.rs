@@ -0,0 +1,208 @@
+#[cfg(feature = "nightly")]
+use core::simd::{
+    cmp::{SimdPartialEq, SimdPartialOrd},
+    num::SimdUint,
+};
+
+use wide::CmpEq;
+
+use crate::classify::{COUNT_CLASS_RANGES, ge_wide256};
+
+/// Lower bounds of AFL's hitcount buckets 1, 2, 3, 4-7, 8-15, 16-31, 32-127 and 128+, taken from
+/// `COUNT_CLASS_RANGES`. With the zero bucket, `afl_bucket_histogram_*` count
+/// `BUCKET_THRESHOLDS.len() + 1` buckets.
+pub const BUCKET_THRESHOLDS: [u8; COUNT_CLASS_RANGES.len()] = {
+    let mut thresholds = [0; COUNT_CLASS_RANGES.len()];
+    let mut i = 0;
+    while i < thresholds.len() {
+        thresholds[i] = COUNT_CLASS_RANGES[i].0;
+        i += 1;
+    }
+    thresholds
+};
+
+/// Entries per bucket, the zero bucket first
+pub type BucketHistogram = [usize; BUCKET_THRESHOLDS.len() + 1];
+
+/// u8 lane counters of masks overflow after this many vectors
+pub(crate) const U8_BATCH: usize = u8::MAX as usize;
+
+pub fn afl_count_nonzero_naive(map: &[u8]) -> usize {
+    map.iter().filter(|it| **it != 0).count()
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_count_nonzero_simd(map: &[u8]) -> usize {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let (body, tail) = map.split_at(map.len() - map.len() % BS);
+    let zero = VectorType::splat(0);
+    let one = VectorType::splat(1);
+    let mut count = 0;
+
+    for batch in body.chunks(BS * U8_BATCH) {
+        let mut acc = zero;
+        for chunk in batch.chunks_exact(BS) {
+            let items = VectorType::from_slice(chunk);
+            acc += items.simd_ne(zero).select(one, zero);
+        }
+        count += acc.cast::<u16>().reduce_sum() as usize;
+    }
+
+    count + afl_count_nonzero_naive(tail)
+}
+
+pub fn afl_count_nonzero_wide256(map: &[u8]) -> usize {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let (body, tail) = map.split_at(map.len() - map.len() % BS);
+    let one = VectorType::new([1; BS]);
+    let mut count = 0;
+
+    for batch in body.chunks(BS * U8_BATCH) {
+        let mut acc = VectorType::ZERO;
+        for chunk in batch.chunks_exact(BS) {
+            let items = VectorType::new(chunk.try_into().unwrap());
+            // lanes equal to zero are all ones, add 1 for the others
+            acc += (items.cmp_eq(VectorType::ZERO) & one) ^ one;
+        }
+        count += acc.to_array().iter().map(|c| *c as usize).sum::<usize>();
+    }
+
+    count + afl_count_nonzero_naive(tail)
+}
+
+pub fn afl_count_bits_naive(map: &[u8]) -> usize {
+    map.iter().map(|it| it.count_ones() as usize).sum()
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_count_bits_simd(map: &[u8]) -> usize {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    // at most 8 bits per lane and vector
+    const BATCH: usize = U8_BATCH / 8;
+    let (body, tail) = map.split_at(map.len() - map.len() % BS);
+    let mut count = 0;
+
+    for batch in body.chunks(BS * BATCH) {
+        let mut acc = VectorType::splat(0);
+        for chunk in batch.chunks_exact(BS) {
+            acc += VectorType::from_slice(chunk).count_ones();
+        }
+        count += acc.cast::<u16>().reduce_sum() as usize;
+    }
+
+    count + afl_count_bits_naive(tail)
+}
+
+pub fn afl_count_bits_wide256(map: &[u8]) -> usize {
+    // wide has no popcount, count the bits of each byte in u16 lanes with SWAR
+    type VectorType = wide::u16x16;
+    const BS: usize = 32;
+    // at most 16 bits per lane and vector
+    const BATCH: usize = u16::MAX as usize / 16;
+    let (body, tail) = map.split_at(map.len() - map.len() % BS);
+    let m1 = VectorType::new([0x5555; 16]);
+    let m2 = VectorType::new([0x3333; 16]);
+    let m4 = VectorType::new([0x0f0f; 16]);
+    let low = VectorType::new([0x00ff; 16]);
+    let mut count = 0;
+
+    for batch in body.chunks(BS * BATCH) {
+        let mut acc = VectorType::ZERO;
+        for chunk in batch.chunks_exact(BS) {
+            let buf: [u8; BS] = chunk.try_into().unwrap();
+            let x = VectorType::new(unsafe { core::mem::transmute::<[u8; BS], [u16; 16]>(buf) });
+            let x = x - ((x >> 1) & m1);
+            let x = (x & m2) + ((x >> 2) & m2);
+            let x = (x + (x >> 4)) & m4;
+            acc = acc + (x & low) + (x >> 8);
+        }
+        count += acc.to_array().iter().map(|c| *c as usize).sum::<usize>();
+    }
+
+    count + afl_count_bits_naive(tail)
+}
+
+pub fn afl_bucket_histogram_naive(map: &[u8]) -> BucketHistogram {
+    let mut histogram = [0; BUCKET_THRESHOLDS.len() + 1];
+    for it in map.iter() {
+        let bucket = BUCKET_THRESHOLDS.iter().filter(|t| *it >= **t).count();
+        histogram[bucket] += 1;
+    }
+    histogram
+}
+
+/// Turn the number of entries `>=` each threshold into entries per bucket
+fn histogram_from_ge(len: usize, ge: &[usize; BUCKET_THRESHOLDS.len()]) -> BucketHistogram {
+    let mut histogram = [0; BUCKET_THRESHOLDS.len() + 1];
+    histogram[0] = len - ge[0];
+    for i in 0..ge.len() {
+        histogram[i + 1] = ge[i] - ge.get(i + 1).copied().unwrap_or(0);
+    }
+    histogram
+}
+
+fn add_histograms(mut lhs: BucketHistogram, rhs: BucketHistogram) -> BucketHistogram {
+    for (l, r) in lhs.iter_mut().zip(rhs) {
+        *l += r;
+    }
+    lhs
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_bucket_histogram_simd(map: &[u8]) -> BucketHistogram {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let (body, tail) = map.split_at(map.len() - map.len() % BS);
+    let zero = VectorType::splat(0);
+    let one = VectorType::splat(1);
+    let thresholds = BUCKET_THRESHOLDS.map(VectorType::splat);
+    let mut ge = [0; BUCKET_THRESHOLDS.len()];
+
+    for batch in body.chunks(BS * U8_BATCH) {
+        let mut acc = [zero; BUCKET_THRESHOLDS.len()];
+        for chunk in batch.chunks_exact(BS) {
+            let items = VectorType::from_slice(chunk);
+            for (acc, threshold) in acc.iter_mut().zip(thresholds.iter()) {
+                *acc += items.simd_ge(*threshold).select(one, zero);
+            }
+        }
+        for (ge, acc) in ge.iter_mut().zip(acc.iter()) {
+            *ge += acc.cast::<u16>().reduce_sum() as usize;
+        }
+    }
+
+    add_histograms(
+        histogram_from_ge(body.len(), &ge),
+        afl_bucket_histogram_naive(tail),
+    )
+}
+
+pub fn afl_bucket_histogram_wide256(map: &[u8]) -> BucketHistogram {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let (body, tail) = map.split_at(map.len() - map.len() % BS);
+    let one = VectorType::new([1; BS]);
+    let thresholds = BUCKET_THRESHOLDS.map(|t| VectorType::new([t; BS]));
+    let mut ge = [0; BUCKET_THRESHOLDS.len()];
+
+    for batch in body.chunks(BS * U8_BATCH) {
+        let mut acc = [VectorType::ZERO; BUCKET_THRESHOLDS.len()];
+        for chunk in batch.chunks_exact(BS) {
+            let items = VectorType::new(chunk.try_into().unwrap());
+            for (acc, threshold) in acc.iter_mut().zip(thresholds.iter()) {
+                *acc += ge_wide256(items, *threshold) & one;
+            }
+        }
+        for (ge, acc) in ge.iter_mut().zip(acc.iter()) {
+            *ge += acc.to_array().iter().map(|c| *c as usize).sum::<usize>();
+        }
+    }
+
+    add_histograms(
+        histogram_from_ge(body.len(), &ge),
+        afl_bucket_histogram_naive(tail),
+    )
+}
@@ -0,0 +1,301 @@
+#[cfg(feature = "nightly")]
+use core::simd::cmp::{SimdOrd, SimdPartialEq};
+
+use alloc::{vec, vec::Vec};
+use wide::CmpEq;
+
+#[cfg(feature = "nightly")]
+use crate::classify::classify_simd;
+use crate::classify::classify_wide256;
+use crate::counts::COUNT_CLASS_LOOKUP;
+
+/// Transform `map` in place with `vector`, or `scalar` for the tail, and check the transformed
+/// map against `hist` in the same pass. Without `NV` the whole map is still transformed, only the
+/// comparisons stop at the first novelty.
+#[cfg(feature = "nightly")]
+#[inline]
+fn transform_cov_simd<const NV: bool>(
+    hist: &[u8],
+    map: &mut [u8],
+    vector: impl Fn(core::simd::u8x32) -> core::simd::u8x32,
+    scalar: impl Fn(u8) -> u8,
+) -> (bool, Vec<usize>) {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let size = map.len();
+    let body = size - size % BS;
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for i in (0..body).step_by(BS) {
+        let items = vector(VectorType::from_slice(&map[i..i + BS]));
+        items.copy_to_slice(&mut map[i..i + BS]);
+        if NV || !interesting {
+            let history = VectorType::from_slice(&hist[i..i + BS]);
+            if items.simd_max(history) != history {
+                interesting = true;
+                if NV {
+                    unsafe {
+                        for j in i..(i + BS) {
+                            if *map.get_unchecked(j) > *hist.get_unchecked(j) {
+                                novelties.push(j);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for j in body..size {
+        map[j] = scalar(map[j]);
+        if map[j] > hist[j] {
+            interesting = true;
+            if NV {
+                novelties.push(j);
+            }
+        }
+    }
+
+    (interesting, novelties)
+}
+
+/// Same as `transform_cov_simd`
+#[inline]
+fn transform_cov_wide256<const NV: bool>(
+    hist: &[u8],
+    map: &mut [u8],
+    vector: impl Fn(wide::u8x32) -> wide::u8x32,
+    scalar: impl Fn(u8) -> u8,
+) -> (bool, Vec<usize>) {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let size = map.len();
+    let body = size - size % BS;
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for i in (0..body).step_by(BS) {
+        let items = vector(VectorType::new(map[i..i + BS].try_into().unwrap()));
+        map[i..i + BS].copy_from_slice(&items.to_array());
+        if NV || !interesting {
+            let history = VectorType::new(hist[i..i + BS].try_into().unwrap());
+            if items.max(history) != history {
+                interesting = true;
+                if NV {
+                    unsafe {
+                        for j in i..(i + BS) {
+                            if *map.get_unchecked(j) > *hist.get_unchecked(j) {
+                                novelties.push(j);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for j in body..size {
+        map[j] = scalar(map[j]);
+        if map[j] > hist[j] {
+            interesting = true;
+            if NV {
+                novelties.push(j);
+            }
+        }
+    }
+
+    (interesting, novelties)
+}
+
+/// Classify the counts of `map` in place and check the classified map against `hist` in one
+/// pass. Same as `afl_classify_counts_naive16` followed by a `cov` kernel.
+#[cfg(feature = "nightly")]
+pub fn afl_classify_cov_simd<const NV: bool>(hist: &[u8], map: &mut [u8]) -> (bool, Vec<usize>) {
+    transform_cov_simd::<NV>(hist, map, classify_simd, |it| {
+        COUNT_CLASS_LOOKUP[it as usize]
+    })
+}
+
+pub fn afl_classify_cov_wide256<const NV: bool>(hist: &[u8], map: &mut [u8]) -> (bool, Vec<usize>) {
+    transform_cov_wide256::<NV>(hist, map, classify_wide256, |it| {
+        COUNT_CLASS_LOOKUP[it as usize]
+    })
+}
+
+/// Check `map` against the crash or timeout `virgin` map like AFL's `has_new_bits`: an entry is
+/// novel if it has bits that are still set in `virgin`, and those bits are then cleared, so
+/// `virgin` starts as all 0xff. Unlike the `cov` kernels the whole map is checked even without
+/// `NV`, since `virgin` has to be updated either way.
+pub fn afl_virgin_naive<const NV: bool>(virgin: &mut [u8], map: &[u8]) -> (bool, Vec<usize>) {
+    let mut novelties = vec![];
+    let interesting = virgin_scalar::<NV>(virgin, map, 0, &mut novelties);
+    (interesting, novelties)
+}
+
+/// `afl_virgin_naive` over `map` starting at entry `offset` of the whole map
+#[inline]
+fn virgin_scalar<const NV: bool>(
+    virgin: &mut [u8],
+    map: &[u8],
+    offset: usize,
+    novelties: &mut Vec<usize>,
+) -> bool {
+    let mut interesting = false;
+    for (j, (untouched, it)) in virgin.iter_mut().zip(map.iter()).enumerate() {
+        if *it & *untouched != 0 {
+            interesting = true;
+            if NV {
+                novelties.push(offset + j);
+            }
+            *untouched &= !*it;
+        }
+    }
+    interesting
+}
+
+/// Clear the bits of the block `items` at `i` in `virgin`, and return whether any were set.
+/// `virgin` is only written for novel blocks, which are rare.
+#[cfg(feature = "nightly")]
+#[inline]
+fn virgin_block_simd<const NV: bool>(
+    virgin: &mut [u8],
+    i: usize,
+    items: core::simd::u8x32,
+    novelties: &mut Vec<usize>,
+) -> bool {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let untouched = VectorType::from_slice(&virgin[i..i + BS]);
+    let new_bits = items & untouched;
+    if new_bits == VectorType::splat(0) {
+        return false;
+    }
+    (untouched ^ new_bits).copy_to_slice(&mut virgin[i..i + BS]);
+    if NV {
+        for (j, it) in new_bits.to_array().iter().enumerate() {
+            if *it != 0 {
+                novelties.push(i + j);
+            }
+        }
+    }
+    true
+}
+
+/// Same as `virgin_block_simd`
+#[inline]
+fn virgin_block_wide256<const NV: bool>(
+    virgin: &mut [u8],
+    i: usize,
+    items: wide::u8x32,
+    novelties: &mut Vec<usize>,
+) -> bool {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let untouched = VectorType::new(virgin[i..i + BS].try_into().unwrap());
+    let new_bits = items & untouched;
+    if new_bits == VectorType::ZERO {
+        return false;
+    }
+    virgin[i..i + BS].copy_from_slice(&(untouched ^ new_bits).to_array());
+    if NV {
+        for (j, it) in new_bits.to_array().iter().enumerate() {
+            if *it != 0 {
+                novelties.push(i + j);
+            }
+        }
+    }
+    true
+}
+
+/// `afl_virgin_naive` with `std::simd`, the second pass after `afl_simplify_trace_*`
+#[cfg(feature = "nightly")]
+pub fn afl_virgin_simd<const NV: bool>(virgin: &mut [u8], map: &[u8]) -> (bool, Vec<usize>) {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let body = map.len() - map.len() % BS;
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for i in (0..body).step_by(BS) {
+        let items = VectorType::from_slice(&map[i..i + BS]);
+        interesting |= virgin_block_simd::<NV>(virgin, i, items, &mut novelties);
+    }
+    interesting |= virgin_scalar::<NV>(&mut virgin[body..], &map[body..], body, &mut novelties);
+
+    (interesting, novelties)
+}
+
+/// `afl_virgin_naive` with `wide`, the second pass after `afl_simplify_trace_*`
+pub fn afl_virgin_wide256<const NV: bool>(virgin: &mut [u8], map: &[u8]) -> (bool, Vec<usize>) {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let body = map.len() - map.len() % BS;
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for i in (0..body).step_by(BS) {
+        let items = VectorType::new(map[i..i + BS].try_into().unwrap());
+        interesting |= virgin_block_wide256::<NV>(virgin, i, items, &mut novelties);
+    }
+    interesting |= virgin_scalar::<NV>(&mut virgin[body..], &map[body..], body, &mut novelties);
+
+    (interesting, novelties)
+}
+
+/// Simplify the trace of a crash or timeout in place, like `afl_simplify_trace_naive`, and check
+/// it against the crash or timeout `virgin` map in one pass, like `afl_virgin_naive`. The crash
+/// is unique if the first element is true.
+#[cfg(feature = "nightly")]
+pub fn afl_simplify_cov_simd<const NV: bool>(
+    virgin: &mut [u8],
+    map: &mut [u8],
+) -> (bool, Vec<usize>) {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let zero = VectorType::splat(0);
+    let hit = VectorType::splat(0x80);
+    let not_hit = VectorType::splat(0x1);
+    let body = map.len() - map.len() % BS;
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for i in (0..body).step_by(BS) {
+        let items = VectorType::from_slice(&map[i..i + BS]);
+        let items = items.simd_eq(zero).select(not_hit, hit);
+        items.copy_to_slice(&mut map[i..i + BS]);
+        interesting |= virgin_block_simd::<NV>(virgin, i, items, &mut novelties);
+    }
+    for it in map[body..].iter_mut() {
+        *it = if *it == 0 { 0x1 } else { 0x80 };
+    }
+    interesting |= virgin_scalar::<NV>(&mut virgin[body..], &map[body..], body, &mut novelties);
+
+    (interesting, novelties)
+}
+
+pub fn afl_simplify_cov_wide256<const NV: bool>(
+    virgin: &mut [u8],
+    map: &mut [u8],
+) -> (bool, Vec<usize>) {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let hit = VectorType::new([0x80; 32]);
+    let not_hit = VectorType::new([0x1; 32]);
+    let body = map.len() - map.len() % BS;
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for i in (0..body).step_by(BS) {
+        let items = VectorType::new(map[i..i + BS].try_into().unwrap());
+        let items = items.cmp_eq(VectorType::ZERO).blend(not_hit, hit);
+        map[i..i + BS].copy_from_slice(&items.to_array());
+        interesting |= virgin_block_wide256::<NV>(virgin, i, items, &mut novelties);
+    }
+    for it in map[body..].iter_mut() {
+        *it = if *it == 0 { 0x1 } else { 0x80 };
+    }
+    interesting |= virgin_scalar::<NV>(&mut virgin[body..], &map[body..], body, &mut novelties);
+
+    (interesting, novelties)
+}
@@ -0,0 +1,180 @@
+#[cfg(feature = "nightly")]
+use core::simd::u64x4;
+
+#[cfg(feature = "nightly")]
+use crate::classify::classify_simd;
+use crate::classify::classify_wide256;
+use crate::counts::COUNT_CLASS_LOOKUP;
+
+/// AFL's seed for `hash32` of the trace bits
+pub const HASH_CONST: u32 = 0xa5b35705;
+
+/// Lanes of the lane-parallel hash
+const LANES: usize = 4;
+
+/// Bytes consumed per step of the lane-parallel hash, one u64 per lane
+const STRIPE: usize = LANES * size_of::<u64>();
+
+/// Initial key of each lane
+const KEYS: [u64; LANES] = [
+    0x9e3779b185ebca87,
+    0xc2b2ae3d27d4eb4f,
+    0x165667b19e3779f9,
+    0x85ebca77c2b2ae63,
+];
+
+/// Keys advance by this after each stripe, so equal stripes at different offsets hash differently
+const KEY_STEP: u64 = 0x27d4eb2f165667c5;
+
+const PRIME: u64 = 0x9fb21c651e98df25;
+
+/// murmur3's 64-bit finalizer
+#[inline]
+fn fmix64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+    h
+}
+
+/// AFL's `hash32`. Like the original it hashes whole u64 words, trailing bytes are ignored.
+pub fn afl_hash32(map: &[u8], seed: u32) -> u32 {
+    let mut h1 = u64::from(seed ^ map.len() as u32);
+    for word in map.chunks_exact(size_of::<u64>()) {
+        let mut k1 = u64::from_ne_bytes(word.try_into().unwrap());
+        k1 = k1.wrapping_mul(0x87c37b91114253d5);
+        k1 = k1.rotate_left(31);
+        k1 = k1.wrapping_mul(0x4cf5ad432745937f);
+        h1 ^= k1;
+        h1 = h1.rotate_left(27);
+        h1 = h1.wrapping_mul(5).wrapping_add(0x52dce729);
+    }
+    fmix64(h1) as u32
+}
+
+/// One lane step: add the word and the 32x32->64 product of the keyed word's halves. No
+/// dependency between lanes and no full 64-bit multiply, so it maps onto SIMD lanes.
+#[inline]
+fn accumulate(acc: u64, word: u64, key: u64) -> u64 {
+    let dk = word ^ key;
+    acc.wrapping_add(word)
+        .wrapping_add((dk & 0xffff_ffff).wrapping_mul(dk >> 32))
+}
+
+/// Combine the lanes and the tail shorter than a stripe
+fn finish(acc: [u64; LANES], tail: &[u8], len: usize, seed: u64) -> u64 {
+    let mut h = seed ^ (len as u64).wrapping_mul(PRIME);
+    for lane in acc {
+        h = fmix64(h ^ lane).rotate_left(27).wrapping_mul(PRIME);
+    }
+    for it in tail {
+        h = (h ^ u64::from(*it)).wrapping_mul(PRIME);
+    }
+    fmix64(h)
+}
+
+#[inline]
+fn stripe_words(stripe: &[u8]) -> [u64; LANES] {
+    let buf: [u8; STRIPE] = stripe.try_into().unwrap();
+    unsafe { core::mem::transmute::<[u8; STRIPE], [u64; LANES]>(buf) }
+}
+
+/// The lane-parallel hash, one lane at a time
+pub fn afl_hash_lanes_naive(map: &[u8], seed: u64) -> u64 {
+    let mut acc = [seed; LANES];
+    let mut keys = KEYS;
+    let mut stripes = map.chunks_exact(STRIPE);
+    for stripe in stripes.by_ref() {
+        let words = stripe_words(stripe);
+        for i in 0..LANES {
+            acc[i] = accumulate(acc[i], words[i], keys[i]);
+            keys[i] = keys[i].wrapping_add(KEY_STEP);
+        }
+    }
+    finish(acc, stripes.remainder(), map.len(), seed)
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_hash_lanes_simd(map: &[u8], seed: u64) -> u64 {
+    let mut acc = u64x4::splat(seed);
+    let mut keys = u64x4::from_array(KEYS);
+    let step = u64x4::splat(KEY_STEP);
+    let low = u64x4::splat(0xffff_ffff);
+    let mut stripes = map.chunks_exact(STRIPE);
+    for stripe in stripes.by_ref() {
+        let words = u64x4::from_array(stripe_words(stripe));
+        let dk = words ^ keys;
+        acc += words + (dk & low) * (dk >> 32);
+        keys += step;
+    }
+    finish(acc.to_array(), stripes.remainder(), map.len(), seed)
+}
+
+pub fn afl_hash_lanes_wide(map: &[u8], seed: u64) -> u64 {
+    type VectorType = wide::u64x4;
+    let mut acc = VectorType::splat(seed);
+    let mut keys = VectorType::new(KEYS);
+    let step = VectorType::splat(KEY_STEP);
+    let low = VectorType::splat(0xffff_ffff);
+    let mut stripes = map.chunks_exact(STRIPE);
+    for stripe in stripes.by_ref() {
+        let words = VectorType::new(stripe_words(stripe));
+        let dk = words ^ keys;
+        acc = acc + words + (dk & low) * (dk >> 32);
+        keys += step;
+    }
+    finish(acc.to_array(), stripes.remainder(), map.len(), seed)
+}
+
+/// Classify the counts in place and hash the classified map in the same pass. Equal to
+/// `afl_classify_counts_naive16` followed by `afl_hash_lanes_naive`.
+#[cfg(feature = "nightly")]
+pub fn afl_classify_hash_simd(map: &mut [u8], seed: u64) -> u64 {
+    type VectorType = core::simd::u8x32;
+    let len = map.len();
+    let mut acc = u64x4::splat(seed);
+    let mut keys = u64x4::from_array(KEYS);
+    let step = u64x4::splat(KEY_STEP);
+    let low = u64x4::splat(0xffff_ffff);
+    let mut stripes = map.chunks_exact_mut(STRIPE);
+    for stripe in stripes.by_ref() {
+        let classified = classify_simd(VectorType::from_slice(stripe));
+        classified.copy_to_slice(stripe);
+        let words = u64x4::from_array(stripe_words(classified.as_array()));
+        let dk = words ^ keys;
+        acc += words + (dk & low) * (dk >> 32);
+        keys += step;
+    }
+    let tail = stripes.into_remainder();
+    for it in tail.iter_mut() {
+        *it = COUNT_CLASS_LOOKUP[*it as usize];
+    }
+    finish(acc.to_array(), tail, len, seed)
+}
+
+/// Same as `afl_classify_hash_simd`
+pub fn afl_classify_hash_wide256(map: &mut [u8], seed: u64) -> u64 {
+    type VectorType = wide::u64x4;
+    let len = map.len();
+    let mut acc = VectorType::splat(seed);
+    let mut keys = VectorType::new(KEYS);
+    let step = VectorType::splat(KEY_STEP);
+    let low = VectorType::splat(0xffff_ffff);
+    let mut stripes = map.chunks_exact_mut(STRIPE);
+    for stripe in stripes.by_ref() {
+        let classified = classify_wide256(wide::u8x32::new(stripe.as_ref().try_into().unwrap()));
+        let bytes = classified.to_array();
+        stripe.copy_from_slice(&bytes);
+        let words = VectorType::new(stripe_words(&bytes));
+        let dk = words ^ keys;
+        acc = acc + words + (dk & low) * (dk >> 32);
+        keys += step;
+    }
+    let tail = stripes.into_remainder();
+    for it in tail.iter_mut() {
+        *it = COUNT_CLASS_LOOKUP[*it as usize];
+    }
+    finish(acc.to_array(), tail, len, seed)
+}
@@ -1,6 +1,28 @@
-#![feature(portable_simd)]
+#![no_std]
+#![cfg_attr(feature = "nightly", feature(portable_simd))]
 
-// Split to a lib for perf
+// Split to a lib for perf. The kernels are `no_std`, the ones returning or keeping a `Vec` need
+// `alloc` and the threaded ones `std`.
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod classify;
 pub mod counts;
 pub mod cov;
+pub mod density;
+#[cfg(feature = "alloc")]
+pub mod fused;
+pub mod hash;
+#[cfg(target_arch = "aarch64")]
+pub mod neon;
+#[cfg(feature = "alloc")]
+pub mod packed;
+#[cfg(feature = "std")]
+pub mod parallel;
+pub mod reset;
+#[cfg(feature = "alloc")]
+pub mod sparse;
+pub mod stability;
@@ -1,22 +1,154 @@
+use cache::{CacheControl, CacheState};
 use chrono::Utc;
-use clap::Parser;
-use counts::afl_simplify_trace_naive;
+use clap::{Parser, ValueEnum};
 use itertools::Itertools;
-use rand::{RngCore, rngs::ThreadRng};
+use perf::{PerfCounters, PerfTotals};
+use preflight::{Preflight, allowed_cpus, pin_to_cpu};
+use rand::{Rng, RngCore, rngs::ThreadRng};
+use std::{
+    path::PathBuf,
+    sync::{
+        Barrier,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
 
+use libafl_simd_bench::classify::BucketScheme;
 use libafl_simd_bench::counts::*;
 use libafl_simd_bench::cov::*;
+use libafl_simd_bench::density::*;
+use libafl_simd_bench::fused::*;
+use libafl_simd_bench::hash::*;
+#[cfg(target_arch = "aarch64")]
+use libafl_simd_bench::neon::*;
+use libafl_simd_bench::packed::*;
+use libafl_simd_bench::parallel::{WorkerPool, afl_parallel};
+use libafl_simd_bench::reset::*;
+use libafl_simd_bench::sparse::*;
+use libafl_simd_bench::stability::*;
 
-mod counts;
+mod cache;
+mod perf;
+mod preflight;
+
+/// Named `BucketScheme`s for `--buckets`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BucketPreset {
+    Afl,
+    Log2,
+    Coarse,
+    Binary,
+}
+
+impl BucketPreset {
+    fn scheme(self) -> BucketScheme {
+        match self {
+            Self::Afl => BucketScheme::afl(),
+            Self::Log2 => BucketScheme::log2(),
+            Self::Coarse => BucketScheme::coarse(),
+            Self::Binary => BucketScheme::binary(),
+        }
+    }
+}
 
 #[derive(Parser)]
 struct CLI {
-    #[arg(short, long)]
+    /// Map size. Not required since `sweep` and `reset` take their sizes from `--min-map` to
+    /// `--max-map` instead, the other programs default to 2 MiB.
+    #[arg(short, long, default_value_t = 2097152)]
     pub map: usize,
     #[arg(short, long)]
     pub rounds: usize,
     #[arg(short, long, default_value = "cov")]
     pub program: String,
+    /// Cache states to measure, comma separated
+    #[arg(short, long, value_enum, value_delimiter = ',', default_value = "hot")]
+    pub cache: Vec<CacheState>,
+    /// Smallest map size for `sweep`
+    #[arg(long, default_value_t = 4096)]
+    pub min_map: usize,
+    /// Largest map size for `sweep`
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    pub max_map: usize,
+    /// Geometric factor between two map sizes for `sweep`
+    #[arg(long, default_value_t = 2)]
+    pub step: usize,
+    /// Fraction of non-zero map entries for programs with dense maps
+    #[arg(long, default_value_t = 0.5)]
+    pub density: f64,
+    /// Collect hardware counters around each measured round
+    #[arg(long)]
+    pub perf: bool,
+    /// Also write the environment facts printed before the results to this file
+    #[arg(long)]
+    pub meta: Option<PathBuf>,
+    /// Pin the benchmark thread to this cpu
+    #[arg(long)]
+    pub cpu: Option<usize>,
+    /// Run `cov` kernels on this many threads, pinned to consecutive cpus from `--cpu`, or split
+    /// one map across this many workers for `par`
+    #[arg(short, long, default_value_t = 1)]
+    pub threads: usize,
+    /// Bucket schemes for `buckets`, comma separated
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "afl,log2,coarse,binary"
+    )]
+    pub buckets: Vec<BucketPreset>,
+}
+
+type CovKernel = fn(&[u8], &[u8]) -> (bool, Vec<usize>);
+
+/// Kernels that always scan the full map, so the time per round is proportional to the map size
+fn cov_kernels() -> Vec<(&'static str, CovKernel)> {
+    let mut kernels: Vec<(&str, CovKernel)> = vec![(
+        "default_novel",
+        afl_default_impl::<true, MaxReducer, DifferentIsNovel>,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("libafl_simd_novel", afl_nightly_simd::<true>));
+    kernels.extend([
+        ("wide128_novel", afl_stable_wide_128::<true> as CovKernel),
+        ("wide256_novel", afl_stable_wide_256::<true>),
+        ("autovec_novel", afl_autovec::<true>),
+    ]);
+    #[cfg(target_arch = "aarch64")]
+    kernels.push(("neon_novel", afl_neon::<true>));
+    kernels
+}
+
+type CovIntoKernel = fn(&[u8], &[u8], &mut Vec<usize>) -> bool;
+
+/// `cov_kernels` writing into a reused buffer
+fn cov_into_kernels() -> Vec<(&'static str, CovIntoKernel)> {
+    let mut kernels: Vec<(&str, CovIntoKernel)> = vec![(
+        "default_novel_into",
+        afl_default_impl_into::<true, MaxReducer, DifferentIsNovel, _>,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("libafl_simd_novel_into", afl_nightly_simd_into::<true, _>));
+    kernels.extend([
+        (
+            "wide128_novel_into",
+            afl_stable_wide_128_into::<true, _> as CovIntoKernel,
+        ),
+        ("wide256_novel_into", afl_stable_wide_256_into::<true, _>),
+        ("autovec_novel_into", afl_autovec_into::<true, _>),
+    ]);
+    #[cfg(target_arch = "aarch64")]
+    kernels.push(("neon_novel_into", afl_neon_into::<true, _>));
+    kernels
+}
+
+/// Result of measuring one kernel over some rounds
+struct Rounds {
+    tms: Vec<chrono::TimeDelta>,
+    /// Total map bytes scanned over all rounds
+    bytes: usize,
+    counters: Option<PerfTotals>,
 }
 
 fn measure_cov<F>(f: F, hist: &[u8], map: &[u8]) -> (chrono::TimeDelta, bool, Vec<usize>)
@@ -29,6 +161,21 @@ where
     (after - before, interesting, novs)
 }
 
+fn measure_cov_into<F>(
+    f: F,
+    hist: &[u8],
+    map: &[u8],
+    novelties: &mut Vec<usize>,
+) -> (chrono::TimeDelta, bool)
+where
+    F: FnOnce(&[u8], &[u8], &mut Vec<usize>) -> bool,
+{
+    let before = Utc::now();
+    let interesting = f(hist, map, novelties);
+    let after = Utc::now();
+    (after - before, interesting)
+}
+
 fn measure_simpliy_counts<F>(f: F, map: &mut [u8]) -> chrono::TimeDelta
 where
     F: FnOnce(&mut [u8]) -> (),
@@ -47,6 +194,17 @@ fn random_bits(map: &mut [u8], rng: &mut ThreadRng) {
     map[bytes_idx] |= 1 << bits_idx;
 }
 
+/// Make a `density` fraction of entries non-zero, for workloads with many novelties
+fn fill_density(map: &mut [u8], density: f64, rng: &mut ThreadRng) {
+    for it in map.iter_mut() {
+        *it = if rng.random_bool(density) {
+            rng.random_range(1..=255)
+        } else {
+            0
+        };
+    }
+}
+
 fn clean_vectors(map: &mut [u8]) {
     for it in map.iter_mut() {
         *it = 0;
@@ -59,7 +217,9 @@ fn measure_rounds<F>(
     map: &mut [u8],
     rng: &mut ThreadRng,
     rounds: usize,
-) -> Vec<chrono::Duration>
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds
 where
     F: FnOnce(&[u8], &[u8]) -> (bool, Vec<usize>) + Copy,
 {
@@ -68,9 +228,12 @@ where
     clean_vectors(hist);
     for _ in 0..rounds {
         random_bits(map, rng);
+        cache.prepare(&mut [hist, map]);
         #[cfg(feature = "correctness")]
         {
+            perf.start();
             let (elp, interesting, nov) = measure_cov(f, hist, map);
+            perf.stop();
             let (_, canonical_interesting, canonical_nov) = measure_cov(
                 afl_default_impl::<true, MaxReducer, DifferentIsNovel>,
                 hist,
@@ -86,11 +249,1340 @@ where
         }
         #[cfg(not(feature = "correctness"))]
         {
+            perf.start();
             let (elp, _, _) = measure_cov(f, hist, map);
+            perf.stop();
             outs.push(elp);
         }
     }
-    outs
+    Rounds {
+        tms: outs,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Like `measure_rounds`, but the kernel writes into one novelty buffer reused for all rounds
+fn measure_into_rounds<F>(
+    f: F,
+    hist: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds
+where
+    F: FnOnce(&[u8], &[u8], &mut Vec<usize>) -> bool + Copy,
+{
+    let mut outs = Vec::with_capacity(rounds);
+    let mut novelties = vec![];
+    clean_vectors(map);
+    clean_vectors(hist);
+    for _ in 0..rounds {
+        random_bits(map, rng);
+        cache.prepare(&mut [hist, map]);
+        perf.start();
+        let (elp, _interesting) = measure_cov_into(f, hist, map, &mut novelties);
+        perf.stop();
+        #[cfg(feature = "correctness")]
+        {
+            let (canonical_interesting, canonical_nov) =
+                afl_default_impl::<true, MaxReducer, DifferentIsNovel>(hist, map);
+            if _interesting != canonical_interesting || novelties != canonical_nov {
+                panic!(
+                    "Incorrect! {} vs {}, {:?} vs {:?}",
+                    _interesting, canonical_interesting, novelties, canonical_nov
+                );
+            }
+        }
+        outs.push(elp);
+    }
+    Rounds {
+        tms: outs,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Like `measure_into_rounds`, but into a `FixedNovelties` too small for the novelties of the
+/// later rounds. The kernel has to keep the first novelties that fit and report the overflow.
+#[allow(clippy::too_many_arguments)]
+fn measure_fixed_rounds<'a>(
+    f: fn(&[u8], &[u8], &mut FixedNovelties<'a>) -> bool,
+    hist: &mut [u8],
+    map: &mut [u8],
+    novelties: &mut FixedNovelties<'a>,
+    rng: &mut ThreadRng,
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds {
+    let mut outs = Vec::with_capacity(rounds);
+    clean_vectors(map);
+    clean_vectors(hist);
+    for _ in 0..rounds {
+        random_bits(map, rng);
+        cache.prepare(&mut [hist, map]);
+        let (elp, _interesting) = time_round(perf, || f(hist, map, novelties));
+        #[cfg(feature = "correctness")]
+        {
+            let (canonical_interesting, canonical_nov) =
+                afl_default_impl::<true, MaxReducer, DifferentIsNovel>(hist, map);
+            let stored = canonical_nov.len().min(novelties.capacity());
+            if _interesting != canonical_interesting
+                || novelties.as_ref() != &canonical_nov[..stored]
+                || novelties.overflowed() != (canonical_nov.len() > stored)
+            {
+                panic!(
+                    "Incorrect! {} vs {}, {:?} (overflowed {}) vs {:?}",
+                    _interesting,
+                    canonical_interesting,
+                    novelties.as_ref(),
+                    novelties.overflowed(),
+                    canonical_nov
+                );
+            }
+        }
+        outs.push(elp);
+    }
+    Rounds {
+        tms: outs,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Scan the same `map` every round, writing into one novelty buffer of any kind
+fn measure_novelties_rounds<N: Novelties>(
+    f: fn(&[u8], &[u8], &mut N) -> bool,
+    hist: &mut [u8],
+    map: &mut [u8],
+    novelties: &mut N,
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds {
+    let mut outs = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        cache.prepare(&mut [hist, map]);
+        perf.start();
+        let before = Utc::now();
+        f(hist, map, novelties);
+        let after = Utc::now();
+        perf.stop();
+        outs.push(after - before);
+    }
+    Rounds {
+        tms: outs,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Time one call of `f`, with the hardware counters running around it
+fn time_round<R>(perf: &mut PerfCounters, f: impl FnOnce() -> R) -> (chrono::TimeDelta, R) {
+    perf.start();
+    let before = Utc::now();
+    let out = f();
+    let after = Utc::now();
+    perf.stop();
+    (after - before, out)
+}
+
+/// Run a kernel which only reads `map` for some rounds, checking its result against `canonical`
+fn measure_map_rounds<R>(
+    f: fn(&[u8]) -> R,
+    map: &mut [u8],
+    canonical: &R,
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds
+where
+    R: PartialEq + core::fmt::Debug,
+{
+    let mut outs = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        cache.prepare(&mut [map]);
+        let (elp, out) = time_round(perf, || f(map));
+        if out != *canonical {
+            panic!("Incorrect! {:?} vs {:?}", out, canonical);
+        }
+        outs.push(elp);
+    }
+    Rounds {
+        tms: outs,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Count non-zero entries, set bits and entries per bucket of a map with `--density`
+fn density(
+    args: &CLI,
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    fill_density(map, args.density, rng);
+
+    let nonzero = afl_count_nonzero_naive(map);
+    let mut kernels = vec![(
+        "nonzero_naive",
+        afl_count_nonzero_naive as fn(&[u8]) -> usize,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("nonzero_simd", afl_count_nonzero_simd));
+    kernels.push(("nonzero_wide256", afl_count_nonzero_wide256));
+    for (name, f) in kernels {
+        printout(
+            name,
+            measure_map_rounds(f, map, &nonzero, args.rounds, cache, perf),
+        );
+    }
+
+    let bits = afl_count_bits_naive(map);
+    let mut kernels = vec![("bits_naive", afl_count_bits_naive as fn(&[u8]) -> usize)];
+    #[cfg(feature = "nightly")]
+    kernels.push(("bits_simd", afl_count_bits_simd));
+    kernels.push(("bits_wide256", afl_count_bits_wide256));
+    for (name, f) in kernels {
+        printout(
+            name,
+            measure_map_rounds(f, map, &bits, args.rounds, cache, perf),
+        );
+    }
+
+    let histogram = afl_bucket_histogram_naive(map);
+    let mut kernels = vec![(
+        "histogram_naive",
+        afl_bucket_histogram_naive as fn(&[u8]) -> BucketHistogram,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("histogram_simd", afl_bucket_histogram_simd));
+    kernels.push(("histogram_wide256", afl_bucket_histogram_wide256));
+    for (name, f) in kernels {
+        printout(
+            name,
+            measure_map_rounds(f, map, &histogram, args.rounds, cache, perf),
+        );
+    }
+    println!(
+        "{} non-zero entries, {} set bits, buckets {:?}",
+        nonzero, bits, histogram
+    );
+}
+
+/// Hash a map with `--density`, AFL's `hash32` against the lane-parallel hash, and classify then
+/// hash against both in one pass
+fn hash(
+    args: &CLI,
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    fill_density(map, args.density, rng);
+
+    let hash32 = afl_hash32(map, HASH_CONST);
+    printout(
+        "hash32",
+        measure_map_rounds(
+            |m| afl_hash32(m, HASH_CONST),
+            map,
+            &hash32,
+            args.rounds,
+            cache,
+            perf,
+        ),
+    );
+
+    let seed = u64::from(HASH_CONST);
+    let lanes = afl_hash_lanes_naive(map, seed);
+    let mut kernels = vec![(
+        "lanes_naive",
+        (|m| afl_hash_lanes_naive(m, u64::from(HASH_CONST))) as fn(&[u8]) -> u64,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("lanes_simd", |m| {
+        afl_hash_lanes_simd(m, u64::from(HASH_CONST))
+    }));
+    kernels.push(("lanes_wide256", |m| {
+        afl_hash_lanes_wide(m, u64::from(HASH_CONST))
+    }));
+    for (name, f) in kernels {
+        printout(
+            name,
+            measure_map_rounds(f, map, &lanes, args.rounds, cache, perf),
+        );
+    }
+
+    // classifying is in place, every round starts from the raw counts again
+    init_count_class_16();
+    let raw = map.to_vec();
+    let mut classified = raw.clone();
+    afl_classify_counts_naive16(&mut classified);
+    let canonical = afl_hash_lanes_naive(&classified, seed);
+    let mut kernels = vec![(
+        "classify_then_hash_wide256",
+        (|m: &mut [u8], seed| {
+            afl_classify_counts_naive16(m);
+            afl_hash_lanes_wide(m, seed)
+        }) as fn(&mut [u8], u64) -> u64,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("classify_then_hash_simd", |m, seed| {
+        afl_classify_counts_naive16(m);
+        afl_hash_lanes_simd(m, seed)
+    }));
+    #[cfg(feature = "nightly")]
+    kernels.push(("classify_hash_simd", afl_classify_hash_simd));
+    kernels.push(("classify_hash_wide256", afl_classify_hash_wide256));
+    for (name, f) in kernels {
+        let mut tms = Vec::with_capacity(args.rounds);
+        for _ in 0..args.rounds {
+            map.copy_from_slice(&raw);
+            cache.prepare(&mut [map]);
+            let (elp, out) = time_round(perf, || f(map, seed));
+            assert_eq!(out, canonical, "{name} hashes differently");
+            assert!(*map == classified, "{name} classifies differently");
+            tms.push(elp);
+        }
+        printout(
+            name,
+            Rounds {
+                tms,
+                bytes: map.len() * args.rounds,
+                counters: perf.take(),
+            },
+        );
+    }
+    map.copy_from_slice(&raw);
+    println!("hash32 {:#010x}, lanes {:#018x}", hash32, lanes);
+}
+
+/// Fraction of the entries of a new run that differ from the first run in `stability`
+const UNSTABLE_FRACTION: f64 = 0.01;
+
+type VarBytesKernel = fn(&[u8], &[u8], &mut [u8]) -> usize;
+
+/// Accumulate the variable entries of a run against the first run, like AFL's calibration. The
+/// first run has `--density` non-zero entries, the new run differs in `UNSTABLE_FRACTION` of them.
+fn stability(
+    args: &CLI,
+    first: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    fill_density(first, args.density, rng);
+    for (it, f) in map.iter_mut().zip(first.iter()) {
+        *it = if rng.random_bool(UNSTABLE_FRACTION) {
+            f.wrapping_add(rng.random_range(1..=255))
+        } else {
+            *f
+        };
+    }
+
+    let mut var = vec![0; map.len()];
+    let unstable = afl_var_bytes_naive(first, map, &mut var);
+    let mut kernels = vec![("var_bytes_naive", afl_var_bytes_naive as VarBytesKernel)];
+    #[cfg(feature = "nightly")]
+    kernels.push(("var_bytes_simd", afl_var_bytes_simd));
+    kernels.push(("var_bytes_wide256", afl_var_bytes_wide256));
+    for (name, f) in kernels {
+        let mut tms = Vec::with_capacity(args.rounds);
+        for _ in 0..args.rounds {
+            clean_vectors(&mut var);
+            cache.prepare(&mut [first, map, &mut var]);
+            let (elp, count) = time_round(perf, || f(first, map, &mut var));
+            assert_eq!(count, unstable, "{name} counts differently");
+            tms.push(elp);
+        }
+        printout(
+            name,
+            Rounds {
+                tms,
+                bytes: map.len() * args.rounds,
+                counters: perf.take(),
+            },
+        );
+    }
+    println!("{} unstable entries", unstable);
+}
+
+/// Whether a map is interesting, and its novelties
+type CovResult = (bool, Vec<usize>);
+
+type InPlaceCovKernel = fn(&[u8], &mut [u8]) -> CovResult;
+
+/// Time `f` transforming `map` in place and checking it against `hist`. `map` is restored from
+/// `raw` before each round, the result and the transformed map must match `canonical` and
+/// `transformed`.
+#[allow(clippy::too_many_arguments)]
+fn measure_in_place_rounds(
+    f: InPlaceCovKernel,
+    hist: &[u8],
+    map: &mut [u8],
+    raw: &[u8],
+    canonical: &(bool, Vec<usize>),
+    transformed: &[u8],
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds {
+    let mut tms = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        map.copy_from_slice(raw);
+        cache.prepare(&mut [map]);
+        let (elp, out) = time_round(perf, || f(hist, map));
+        assert!(out == *canonical, "Incorrect! {:?} vs {:?}", out, canonical);
+        assert!(map == transformed, "map transformed differently");
+        tms.push(elp);
+    }
+    Rounds {
+        tms,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Fraction of the entries `fused` lowers in `hist`, which are then novel
+const FUSED_NOVEL_FRACTION: f64 = 0.001;
+
+/// Classify then check novelty in two passes against the fused kernels. The map has `--density`
+/// non-zero entries, `hist` is its classified version with a few entries lowered. Every kernel
+/// has to classify like `COUNT_CLASS_LOOKUP`.
+fn fused(
+    args: &CLI,
+    hist: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    init_count_class_16();
+    fill_density(map, args.density, rng);
+    let raw = map.to_vec();
+    let classified = raw
+        .iter()
+        .map(|it| COUNT_CLASS_LOOKUP[*it as usize])
+        .collect_vec();
+    for (h, m) in hist.iter_mut().zip(classified.iter()) {
+        *h = if rng.random_bool(FUSED_NOVEL_FRACTION) {
+            m / 2
+        } else {
+            *m
+        };
+    }
+    let canonical = afl_default_impl::<true, MaxReducer, DifferentIsNovel>(hist, &classified);
+
+    let mut kernels: Vec<(&str, InPlaceCovKernel)> = vec![];
+    #[cfg(feature = "nightly")]
+    kernels.push(("two_pass_simd", |hist, map| {
+        afl_classify_counts_naive16(map);
+        afl_nightly_simd::<true>(hist, map)
+    }));
+    kernels.push(("two_pass_wide256", |hist, map| {
+        afl_classify_counts_naive16(map);
+        afl_stable_wide_256::<true>(hist, map)
+    }));
+    kernels.push(("two_pass_autovec", |hist, map| {
+        afl_classify_counts_autovec(map);
+        afl_autovec::<true>(hist, map)
+    }));
+    #[cfg(target_arch = "aarch64")]
+    kernels.push(("two_pass_neon", |hist, map| {
+        afl_classify_counts_neon(map);
+        afl_neon::<true>(hist, map)
+    }));
+    #[cfg(feature = "nightly")]
+    kernels.push(("fused_simd", afl_classify_cov_simd::<true>));
+    kernels.push(("fused_wide256", afl_classify_cov_wide256::<true>));
+    for (name, f) in kernels {
+        printout(
+            name,
+            measure_in_place_rounds(
+                f,
+                hist,
+                map,
+                &raw,
+                &canonical,
+                &classified,
+                args.rounds,
+                cache,
+                perf,
+            ),
+        );
+    }
+    map.copy_from_slice(&raw);
+    println!("{} novelties", canonical.1.len());
+}
+
+type VirginKernel = fn(&mut [u8], &mut [u8]) -> CovResult;
+
+/// Time `f` simplifying `map` in place and checking it against `virgin`. `map` and `virgin` are
+/// restored from `raw` and `untouched` before each round, the result, the simplified map and the
+/// updated virgin map must match `canonical`, `simplified` and `updated`.
+#[allow(clippy::too_many_arguments)]
+fn measure_virgin_rounds(
+    f: VirginKernel,
+    virgin: &mut [u8],
+    map: &mut [u8],
+    untouched: &[u8],
+    raw: &[u8],
+    canonical: &CovResult,
+    updated: &[u8],
+    simplified: &[u8],
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds {
+    let mut tms = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        virgin.copy_from_slice(untouched);
+        map.copy_from_slice(raw);
+        cache.prepare(&mut [virgin, map]);
+        let (elp, out) = time_round(perf, || f(virgin, map));
+        assert!(out == *canonical, "Incorrect! {:?} vs {:?}", out, canonical);
+        assert!(map == simplified, "map simplified differently");
+        assert!(virgin == updated, "virgin map updated differently");
+        tms.push(elp);
+    }
+    Rounds {
+        tms,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Simplify a crash trace then check it against the crash virgin map in two passes, against the
+/// fused kernels, with and without novelties. The crash map has `--density` non-zero entries,
+/// the virgin map has the bits of its simplified version cleared except for a few entries, as if
+/// earlier crashes had almost the same trace. `afl_virgin_naive` is the reference.
+fn crash(
+    args: &CLI,
+    virgin: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    fill_density(map, args.density, rng);
+    let raw = map.to_vec();
+    afl_simplify_trace_naive(map);
+    let simplified = map.to_vec();
+    let untouched = simplified
+        .iter()
+        .map(|it| {
+            if rng.random_bool(FUSED_NOVEL_FRACTION) {
+                0xff
+            } else {
+                !it
+            }
+        })
+        .collect_vec();
+    let mut updated = untouched.clone();
+    let novelties = afl_virgin_naive::<true>(&mut updated, &simplified);
+    let unique = (novelties.0, vec![]);
+
+    let mut kernels: Vec<(&str, VirginKernel, &CovResult)> = vec![(
+        "two_pass_naive",
+        |virgin, map| {
+            afl_simplify_trace_naive(map);
+            afl_virgin_naive::<true>(virgin, map)
+        },
+        &novelties,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push((
+        "two_pass_simd",
+        |virgin, map| {
+            afl_simplify_trace_simd256(map);
+            afl_virgin_simd::<true>(virgin, map)
+        },
+        &novelties,
+    ));
+    kernels.push((
+        "two_pass_wide256",
+        |virgin, map| {
+            afl_simplify_trace_wide256(map);
+            afl_virgin_wide256::<true>(virgin, map)
+        },
+        &novelties,
+    ));
+    #[cfg(feature = "nightly")]
+    kernels.push(("fused_simd", afl_simplify_cov_simd::<true>, &novelties));
+    kernels.push((
+        "fused_wide256",
+        afl_simplify_cov_wide256::<true>,
+        &novelties,
+    ));
+    kernels.push((
+        "two_pass_wide256_unique",
+        |virgin, map| {
+            afl_simplify_trace_wide256(map);
+            afl_virgin_wide256::<false>(virgin, map)
+        },
+        &unique,
+    ));
+    #[cfg(feature = "nightly")]
+    kernels.push(("fused_simd_unique", afl_simplify_cov_simd::<false>, &unique));
+    kernels.push((
+        "fused_wide256_unique",
+        afl_simplify_cov_wide256::<false>,
+        &unique,
+    ));
+    for (name, f, canonical) in kernels {
+        printout(
+            name,
+            measure_virgin_rounds(
+                f,
+                virgin,
+                map,
+                &untouched,
+                &raw,
+                canonical,
+                &updated,
+                &simplified,
+                args.rounds,
+                cache,
+                perf,
+            ),
+        );
+    }
+    map.copy_from_slice(&raw);
+    println!("unique {}, {} novelties", unique.0, novelties.1.len());
+}
+
+/// Time `f` transforming `map` in place, restored from `raw` before each round, and check that
+/// the result is `transformed`
+fn measure_transform_rounds(
+    f: impl Fn(&mut [u8]),
+    map: &mut [u8],
+    raw: &[u8],
+    transformed: &[u8],
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds {
+    let mut tms = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        map.copy_from_slice(raw);
+        cache.prepare(&mut [map]);
+        let (elp, ()) = time_round(perf, || f(map));
+        assert!(map == transformed, "map transformed differently");
+        tms.push(elp);
+    }
+    Rounds {
+        tms,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+type TransformKernel<'a> = Box<dyn Fn(&mut [u8]) + 'a>;
+
+/// Classify a map with `--density` non-zero entries with each of `--buckets`, with the generated
+/// lookup tables and SIMD kernels
+fn buckets(
+    args: &CLI,
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    fill_density(map, args.density, rng);
+    let raw = map.to_vec();
+
+    for preset in args.buckets.iter() {
+        let scheme = preset.scheme();
+        println!("Bucket scheme {:?}: {:?}", preset, scheme.ranges());
+        let lookup = scheme.lookup();
+        let lookup16 = scheme.lookup16();
+        let mut classified = raw.clone();
+        BucketScheme::classify_counts_lookup(&lookup, &mut classified);
+
+        let mut kernels: Vec<(&str, TransformKernel)> = vec![
+            (
+                "lookup",
+                Box::new(|map| BucketScheme::classify_counts_lookup(&lookup, map)),
+            ),
+            (
+                "lookup16",
+                Box::new(|map| BucketScheme::classify_counts_lookup16(&lookup16, map)),
+            ),
+        ];
+        #[cfg(feature = "nightly")]
+        kernels.push(("simd", Box::new(|map| scheme.classify_counts_simd(map))));
+        kernels.push((
+            "wide256",
+            Box::new(|map| scheme.classify_counts_wide256(map)),
+        ));
+        #[cfg(target_arch = "aarch64")]
+        kernels.push((
+            "neon",
+            Box::new(|map| classify_counts_lookup_neon(&lookup, map)),
+        ));
+        for (name, f) in kernels {
+            printout(
+                name,
+                measure_transform_rounds(f, map, &raw, &classified, args.rounds, cache, perf),
+            );
+        }
+    }
+    map.copy_from_slice(&raw);
+}
+
+/// Classify a map of `--map` u16 counters with `--density` non-zero entries, spread over all
+/// buckets, in place and into compressed u8 classes
+fn counts16(args: &CLI, rng: &mut ThreadRng, cache: &mut CacheControl, perf: &mut PerfCounters) {
+    let raw = (0..args.map)
+        .map(|_| {
+            if rng.random_bool(args.density) {
+                (rng.random_range(1..=u16::MAX) >> rng.random_range(0..16)).max(1)
+            } else {
+                0
+            }
+        })
+        .collect_vec();
+    let bytes = size_of_val(raw.as_slice());
+
+    let mut classified = raw.clone();
+    afl_classify_counts_u16_naive(&mut classified);
+    let mut map = raw.clone();
+    let mut kernels = vec![("u16_naive", afl_classify_counts_u16_naive as fn(&mut [u16]))];
+    #[cfg(feature = "nightly")]
+    kernels.push(("u16_simd", afl_classify_counts_u16_simd));
+    kernels.push(("u16_wide256", afl_classify_counts_u16_wide256));
+    for (name, f) in kernels {
+        let mut tms = Vec::with_capacity(args.rounds);
+        for _ in 0..args.rounds {
+            map.copy_from_slice(&raw);
+            cache.prepare(&mut [u16_bytes(&mut map)]);
+            let (elp, ()) = time_round(perf, || f(&mut map));
+            assert!(map == classified, "{name} classifies differently");
+            tms.push(elp);
+        }
+        printout(
+            name,
+            Rounds {
+                tms,
+                bytes: bytes * args.rounds,
+                counters: perf.take(),
+            },
+        );
+    }
+
+    let mut compressed = vec![0; args.map];
+    afl_classify_counts_u16_to_u8_naive(&raw, &mut compressed);
+    let mut dst = vec![0; args.map];
+    let mut src = raw.clone();
+    let mut kernels = vec![(
+        "u16_to_u8_naive",
+        afl_classify_counts_u16_to_u8_naive as fn(&[u16], &mut [u8]),
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("u16_to_u8_simd", afl_classify_counts_u16_to_u8_simd));
+    kernels.push(("u16_to_u8_wide256", afl_classify_counts_u16_to_u8_wide256));
+    for (name, f) in kernels {
+        let mut tms = Vec::with_capacity(args.rounds);
+        for _ in 0..args.rounds {
+            dst.fill(0);
+            cache.prepare(&mut [u16_bytes(&mut src), &mut dst]);
+            let (elp, ()) = time_round(perf, || f(&src, &mut dst));
+            assert!(dst == compressed, "{name} classifies differently");
+            tms.push(elp);
+        }
+        printout(
+            name,
+            Rounds {
+                tms,
+                bytes: bytes * args.rounds,
+                counters: perf.take(),
+            },
+        );
+    }
+}
+
+/// The bytes of a u16 map, for `CacheControl::prepare`
+fn u16_bytes(map: &mut [u16]) -> &mut [u8] {
+    let len = size_of_val(map);
+    unsafe { core::slice::from_raw_parts_mut(map.as_mut_ptr() as *mut u8, len) }
+}
+
+type InPlaceKernel = fn(&mut [u8]);
+type OutOfPlaceKernel = fn(&[u8], &mut [u8]);
+
+/// Counts kernels in place and from one buffer into another
+fn counts_to_kernels() -> Vec<(&'static str, InPlaceKernel, OutOfPlaceKernel)> {
+    let mut kernels: Vec<(&str, InPlaceKernel, OutOfPlaceKernel)> = vec![
+        (
+            "classify_naive16",
+            afl_classify_counts_naive16,
+            afl_classify_counts_naive16_to,
+        ),
+        (
+            "simplify_naive",
+            afl_simplify_trace_naive,
+            afl_simplify_trace_naive_to,
+        ),
+        (
+            "simplify_wide128",
+            afl_simplify_trace_wide128,
+            afl_simplify_trace_wide128_to,
+        ),
+        (
+            "simplify_wide256",
+            afl_simplify_trace_wide256,
+            afl_simplify_trace_wide256_to,
+        ),
+    ];
+    #[cfg(feature = "nightly")]
+    kernels.extend([
+        (
+            "simplify_simd128",
+            afl_simplify_trace_simd128 as InPlaceKernel,
+            afl_simplify_trace_simd128_to as OutOfPlaceKernel,
+        ),
+        (
+            "simplify_simd256",
+            afl_simplify_trace_simd256,
+            afl_simplify_trace_simd256_to,
+        ),
+        (
+            "simplify_simd512",
+            afl_simplify_trace_simd512,
+            afl_simplify_trace_simd512_to,
+        ),
+    ]);
+    kernels.push((
+        "simplify_autovec",
+        afl_simplify_trace_autovec,
+        afl_simplify_trace_autovec_to,
+    ));
+    kernels
+}
+
+/// Copy the raw counts then run a counts kernel on the copy, against writing the copy directly
+/// with the out-of-place variant, on a map with `--density` non-zero entries
+fn counts_to(
+    args: &CLI,
+    src: &mut [u8],
+    dst: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    init_count_class_16();
+    fill_density(src, args.density, rng);
+
+    for (name, in_place, to) in counts_to_kernels() {
+        let mut expected = src.to_vec();
+        in_place(&mut expected);
+
+        let mut copy_tms = Vec::with_capacity(args.rounds);
+        for _ in 0..args.rounds {
+            clean_vectors(dst);
+            cache.prepare(&mut [src, dst]);
+            let (elp, ()) = time_round(perf, || {
+                dst.copy_from_slice(src);
+                in_place(dst)
+            });
+            assert!(*dst == expected, "{name} differs");
+            copy_tms.push(elp);
+        }
+        printout(
+            &format!("{}_copy", name),
+            Rounds {
+                tms: copy_tms,
+                bytes: src.len() * args.rounds,
+                counters: perf.take(),
+            },
+        );
+
+        let mut to_tms = Vec::with_capacity(args.rounds);
+        for _ in 0..args.rounds {
+            clean_vectors(dst);
+            cache.prepare(&mut [src, dst]);
+            let (elp, ()) = time_round(perf, || to(src, dst));
+            assert!(*dst == expected, "{name}_to differs");
+            to_tms.push(elp);
+        }
+        printout(
+            &format!("{}_to", name),
+            Rounds {
+                tms: to_tms,
+                bytes: src.len() * args.rounds,
+                counters: perf.take(),
+            },
+        );
+    }
+}
+
+/// Pack a simplified map with `--density` hit entries into a bitset of hit entries, and print
+/// the count range of each bucket
+fn decode(
+    args: &CLI,
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    fill_density(map, args.density, rng);
+    afl_simplify_trace_naive(map);
+
+    let bitset = afl_simplified_to_bitset_naive(map);
+    let mut kernels = vec![(
+        "bitset_naive",
+        afl_simplified_to_bitset_naive as fn(&[u8]) -> Vec<u64>,
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("bitset_simd", afl_simplified_to_bitset_simd));
+    kernels.push(("bitset_wide256", afl_simplified_to_bitset_wide256));
+    for (name, f) in kernels {
+        printout(
+            name,
+            measure_map_rounds(f, map, &bitset, args.rounds, cache, perf),
+        );
+    }
+
+    let buckets = (0..=u8::MAX)
+        .filter_map(|class| afl_bucket_range(class).map(|range| (class, range)))
+        .map(|(class, range)| format!("{}: {}-{}", class, range.start(), range.end()))
+        .join(", ");
+    println!(
+        "{} hit entries, buckets {}",
+        bitset.iter().map(|w| w.count_ones()).sum::<u32>(),
+        buckets
+    );
+}
+
+/// Run `round`, which prepares the cache state of its buffers and returns `time_round`, for
+/// `rounds`, checking each result against `canonical`. Throughput is per `edges`.
+fn measure_fn_rounds<R>(
+    mut round: impl FnMut(&mut CacheControl, &mut PerfCounters) -> (chrono::TimeDelta, R),
+    canonical: &R,
+    edges: usize,
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds
+where
+    R: PartialEq + core::fmt::Debug,
+{
+    let mut tms = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        let (elp, out) = round(cache, perf);
+        assert!(out == *canonical, "Incorrect! {:?} vs {:?}", out, canonical);
+        tms.push(elp);
+    }
+    Rounds {
+        tms,
+        bytes: edges * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// The words of a packed map, for `CacheControl::prepare`
+fn u64_bytes(words: &mut [u64]) -> &mut [u8] {
+    let len = size_of_val(words);
+    unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, len) }
+}
+
+/// Byte map `cov` kernels against packed maps with one bit per edge, at `--map` edges. The map
+/// hits `--density` of the edges, the history misses a few of those.
+fn packed(
+    args: &CLI,
+    hist: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    fill_density(map, args.density, rng);
+    for (h, m) in hist.iter_mut().zip(map.iter()) {
+        *h = if rng.random_bool(FUSED_NOVEL_FRACTION) {
+            0
+        } else {
+            *m
+        };
+    }
+    let edges = map.len();
+
+    println!("Byte maps...");
+    let canonical = afl_default_impl::<true, MaxReducer, DifferentIsNovel>(hist, map);
+    for (name, f) in cov_kernels() {
+        let rounds = measure_fn_rounds(
+            |cache, perf| {
+                cache.prepare(&mut [hist, map]);
+                time_round(perf, || f(hist, map))
+            },
+            &canonical,
+            edges,
+            args.rounds,
+            cache,
+            perf,
+        );
+        printout(name, rounds);
+    }
+
+    println!("Packed maps...");
+    let mut packed_map = PackedMap::new(edges);
+    packed_map.pack_naive(map);
+    let mut packed_hist = PackedMap::new(edges);
+    packed_hist.pack_naive(hist);
+    let hits = packed_map.clone();
+    let mut kernels = vec![(
+        "pack_naive",
+        PackedMap::pack_naive as fn(&mut PackedMap, &[u8]),
+    )];
+    #[cfg(feature = "nightly")]
+    kernels.push(("pack_simd", PackedMap::pack_simd));
+    kernels.push(("pack_wide256", PackedMap::pack_wide256));
+    for (name, pack) in kernels {
+        let rounds = measure_fn_rounds(
+            |cache, perf| {
+                cache.prepare(&mut [map, u64_bytes(packed_map.words_mut())]);
+                let (elp, ()) = time_round(perf, || pack(&mut packed_map, map));
+                (elp, packed_map.clone())
+            },
+            &hits,
+            edges,
+            args.rounds,
+            cache,
+            perf,
+        );
+        printout(name, rounds);
+    }
+
+    // both maps hit the same edges, so the packed novelties are the byte map novelties
+    for (name, f) in [
+        (
+            "packed_novel",
+            afl_packed_cov::<true> as fn(&PackedMap, &PackedMap) -> (bool, Vec<usize>),
+        ),
+        ("packed_wide256_novel", afl_packed_cov_wide256::<true>),
+    ] {
+        let rounds = measure_fn_rounds(
+            |cache, perf| {
+                cache.prepare(&mut [
+                    u64_bytes(packed_hist.words_mut()),
+                    u64_bytes(packed_map.words_mut()),
+                ]);
+                time_round(perf, || f(&packed_hist, &packed_map))
+            },
+            &canonical,
+            edges,
+            args.rounds,
+            cache,
+            perf,
+        );
+        printout(name, rounds);
+    }
+    let rounds = measure_fn_rounds(
+        |cache, perf| {
+            cache.prepare(&mut [
+                map,
+                u64_bytes(packed_hist.words_mut()),
+                u64_bytes(packed_map.words_mut()),
+            ]);
+            time_round(perf, || {
+                packed_map.pack_wide256(map);
+                afl_packed_cov_wide256::<true>(&packed_hist, &packed_map)
+            })
+        },
+        &canonical,
+        edges,
+        args.rounds,
+        cache,
+        perf,
+    );
+    printout("pack_and_packed_wide256_novel", rounds);
+    println!("{} novelties", canonical.1.len());
+}
+
+/// Map densities for `sparse`, from a handful of edges to a full map
+const SPARSE_DENSITIES: [f64; 8] = [0.0001, 0.001, 0.005, 0.01, 0.02, 0.05, 0.2, 1.0];
+
+/// Full scans against scans of the dirty blocks only, over map densities, to find the break-even
+fn sparse(
+    args: &CLI,
+    hist: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    init_count_class_16();
+    let size = map.len();
+    let mut summary = BlockSummary::new(size);
+    let mut reports = vec![];
+
+    println!(
+        "|Density|Dirty blocks|wide256_novel|summarize+sparse_novel|sparse_novel|classify_naive16|sparse_classify|"
+    );
+    println!("|-|-|-|-|-|-|-|");
+    for density in SPARSE_DENSITIES {
+        clean_vectors(hist);
+        fill_density(map, density, rng);
+        let pristine = map.to_vec();
+        let (_, canonical) = afl_default_impl::<true, MaxReducer, DifferentIsNovel>(hist, map);
+
+        let mut classified = pristine.clone();
+        afl_classify_counts_naive16(&mut classified);
+
+        // each variant runs its own rounds, so its hardware counters are its own
+        let full = measure_fn_rounds(
+            |cache, perf| {
+                cache.prepare(&mut [hist, map]);
+                let (elp, (_, nov)) = time_round(perf, || afl_stable_wide_256::<true>(hist, map));
+                (elp, nov)
+            },
+            &canonical,
+            size,
+            args.rounds,
+            cache,
+            perf,
+        );
+        let summarized = measure_fn_rounds(
+            |cache, perf| {
+                cache.prepare(&mut [hist, map]);
+                let (elp, (_, nov)) = time_round(perf, || {
+                    summary.summarize(map);
+                    afl_sparse_wide::<true>(hist, map, &summary)
+                });
+                (elp, nov)
+            },
+            &canonical,
+            size,
+            args.rounds,
+            cache,
+            perf,
+        );
+        // the summary is still there, like one maintained by the instrumentation
+        let prebuilt = measure_fn_rounds(
+            |cache, perf| {
+                cache.prepare(&mut [hist, map]);
+                let (elp, (_, nov)) =
+                    time_round(perf, || afl_sparse_wide::<true>(hist, map, &summary));
+                (elp, nov)
+            },
+            &canonical,
+            size,
+            args.rounds,
+            cache,
+            perf,
+        );
+        let classify = measure_fn_rounds(
+            |cache, perf| {
+                map.copy_from_slice(&pristine);
+                cache.prepare(&mut [map]);
+                let (elp, ()) = time_round(perf, || afl_classify_counts_naive16(map));
+                (elp, map.to_vec())
+            },
+            &classified,
+            size,
+            args.rounds,
+            cache,
+            perf,
+        );
+        let sparse_classify = measure_fn_rounds(
+            |cache, perf| {
+                map.copy_from_slice(&pristine);
+                cache.prepare(&mut [map]);
+                let (elp, ()) = time_round(perf, || afl_sparse_classify_counts(map, &summary));
+                (elp, map.to_vec())
+            },
+            &classified,
+            size,
+            args.rounds,
+            cache,
+            perf,
+        );
+
+        let variants = [
+            ("wide256_novel", full),
+            ("summarize+sparse_novel", summarized),
+            ("sparse_novel", prebuilt),
+            ("classify_naive16", classify),
+            ("sparse_classify", sparse_classify),
+        ];
+        println!(
+            "|{}|{}|{}|",
+            density,
+            summary.dirty_blocks().count(),
+            variants
+                .iter()
+                .map(|(_, rounds)| format!("{:.03}", throughput(size, &rounds.tms)))
+                .join("|")
+        );
+        for (name, rounds) in variants {
+            if let Some(counters) = rounds.counters {
+                reports.push(format!(
+                    "{} {}: {}",
+                    density,
+                    name,
+                    counters.report(rounds.bytes)
+                ));
+            }
+        }
+    }
+    for report in reports {
+        println!("{}", report);
+    }
+    map.fill(0);
+}
+
+/// Dense novelties in each compact representation
+fn compact(
+    args: &CLI,
+    hist: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    clean_vectors(hist);
+    fill_density(map, args.density, rng);
+    let (_, canonical) = afl_default_impl::<true, MaxReducer, DifferentIsNovel>(hist, map);
+
+    let mut usizes: Vec<usize> = vec![];
+    let rounds = measure_novelties_rounds(
+        afl_stable_wide_256_into::<true, _>,
+        hist,
+        map,
+        &mut usizes,
+        args.rounds,
+        cache,
+        perf,
+    );
+    assert_eq!(usizes, canonical, "Incorrect usize novelties!");
+    println!(
+        "wide256_usize: {} novelty bytes",
+        size_of_val(usizes.as_slice())
+    );
+    printout("wide256_usize", rounds);
+
+    let mut u32s: Vec<u32> = vec![];
+    let rounds = measure_novelties_rounds(
+        afl_stable_wide_256_into::<true, _>,
+        hist,
+        map,
+        &mut u32s,
+        args.rounds,
+        cache,
+        perf,
+    );
+    assert_eq!(
+        novelties_to_usize(&u32s),
+        canonical,
+        "Incorrect u32 novelties!"
+    );
+    println!(
+        "wide256_u32: {} novelty bytes",
+        size_of_val(u32s.as_slice())
+    );
+    printout("wide256_u32", rounds);
+
+    for block in [1, 32] {
+        let name = format!("wide256_bitset{}", block);
+        let mut bitset = NoveltyBitset::per_block(map.len(), block);
+        let rounds = measure_novelties_rounds(
+            afl_stable_wide_256_into::<true, _>,
+            hist,
+            map,
+            &mut bitset,
+            args.rounds,
+            cache,
+            perf,
+        );
+        assert_eq!(
+            bitset.to_indices(),
+            canonical
+                .iter()
+                .map(|idx| idx / block * block)
+                .dedup()
+                .collect_vec(),
+            "Incorrect bitset novelties!"
+        );
+        println!("{}: {} novelty bytes", name, size_of_val(bitset.words()));
+        printout(&name, rounds);
+    }
+}
+
+/// Like `measure_rounds`, but `hist` is shared with other threads which start each round together.
+/// Only the `leader` thread brings `hist` into the cache state, the others wait for it at the
+/// barrier. A thread with a wrong result doesn't panic, which would leave the others waiting at
+/// the barrier forever, but flags it in `sync` so all threads stop after the round.
+#[allow(clippy::too_many_arguments)]
+fn measure_shared_rounds<F>(
+    f: F,
+    hist: &[u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    rounds: usize,
+    cache: &mut CacheControl,
+    sync: &RoundSync,
+    leader: bool,
+) -> Result<Vec<chrono::Duration>, String>
+where
+    F: FnOnce(&[u8], &[u8]) -> (bool, Vec<usize>) + Copy,
+{
+    let mut outs = Vec::with_capacity(rounds);
+    #[cfg(feature = "correctness")]
+    let mut error = None;
+    clean_vectors(map);
+    for _ in 0..rounds {
+        random_bits(map, rng);
+        cache.prepare(&mut [map]);
+        if leader {
+            cache.prepare_shared(hist);
+        }
+        sync.barrier.wait();
+        #[cfg(feature = "correctness")]
+        {
+            let (elp, interesting, nov) = measure_cov(f, hist, map);
+            let (_, canonical_interesting, canonical_nov) = measure_cov(
+                afl_default_impl::<true, MaxReducer, DifferentIsNovel>,
+                hist,
+                map,
+            );
+            if interesting != canonical_interesting || nov != canonical_nov {
+                error = Some(format!(
+                    "Incorrect! {} vs {}, {:?} vs {:?}",
+                    interesting, canonical_interesting, nov, canonical_nov
+                ));
+                sync.failed.store(true, Ordering::Relaxed);
+            }
+            outs.push(elp);
+        }
+        #[cfg(not(feature = "correctness"))]
+        {
+            let (elp, _, _) = measure_cov(f, hist, map);
+            outs.push(elp);
+        }
+        // nobody sets `failed` between this barrier and the next round's, so all threads agree
+        sync.barrier.wait();
+        if sync.failed.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    #[cfg(feature = "correctness")]
+    if let Some(error) = error {
+        return Err(error);
+    }
+    Ok(outs)
+}
+
+/// Keeps the threads of `threaded` in lockstep
+struct RoundSync {
+    barrier: Barrier,
+    /// Set during a round by a thread with a wrong result
+    failed: AtomicBool,
 }
 
 fn measure_counts_rounds<F>(
@@ -98,7 +1590,9 @@ fn measure_counts_rounds<F>(
     map: &mut [u8],
     rng: &mut ThreadRng,
     rounds: usize,
-) -> Vec<chrono::Duration>
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds
 where
     F: FnOnce(&mut [u8]) -> () + Copy,
 {
@@ -107,10 +1601,13 @@ where
 
     for _ in 0..rounds {
         random_bits(map, rng);
+        cache.prepare(&mut [map]);
         #[cfg(feature = "correctness")]
         {
             let mut canonical = map.to_vec();
+            perf.start();
             let elp = measure_simpliy_counts(f, map);
+            perf.stop();
             afl_simplify_trace_naive(&mut canonical);
 
             if map != &mut canonical {
@@ -121,15 +1618,22 @@ where
 
         #[cfg(not(feature = "correctness"))]
         {
+            perf.start();
             let elp = measure_simpliy_counts(f, map);
+            perf.stop();
             outs.push(elp);
         }
     }
-    outs
+    Rounds {
+        tms: outs,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
 }
 
-fn printout(ty: &str, tms: Vec<chrono::TimeDelta>) {
-    let tms = tms
+fn printout(ty: &str, rounds: Rounds) {
+    let tms = rounds
+        .tms
         .iter()
         .map(|t| t.to_std().unwrap().as_secs_f64())
         .collect_vec();
@@ -146,112 +1650,635 @@ fn printout(ty: &str, tms: Vec<chrono::TimeDelta>) {
         "{}: avg {:.03}, min {:.03}, max {:.03}, std {:.03}, sum {:.03}",
         ty, mean, min, max, std, sum
     );
+    if let Some(counters) = rounds.counters {
+        println!("{}: {}", ty, counters.report(rounds.bytes));
+    }
 }
 
-fn main() {
-    let args = CLI::parse();
-    let mut map = vec![0; args.map];
-    let mut hist = vec![0; args.map];
-    let mut rand = rand::rng();
+fn mean_secs(tms: &[chrono::TimeDelta]) -> f64 {
+    tms.iter()
+        .map(|t| t.to_std().unwrap().as_secs_f64())
+        .sum::<f64>()
+        / tms.len() as f64
+}
+
+/// Throughput in GB/s, counting the map bytes scanned per round
+fn throughput(size: usize, tms: &[chrono::TimeDelta]) -> f64 {
+    size as f64 / mean_secs(tms) / 1e9
+}
+
+/// GB/s of each kernel over map sizes, as a markdown table. The kernels read both `hist` and
+/// `map`, so both count. Hardware counters follow the table.
+fn sweep(args: &CLI, rng: &mut ThreadRng, cache: &mut CacheControl, perf: &mut PerfCounters) {
+    assert!(args.step > 1, "--step must be at least 2");
+    assert!(args.min_map > 0, "--min-map must be positive");
+    let kernels = cov_kernels();
+    let mut reports = vec![];
+
+    println!("|Map|{}|", kernels.iter().map(|(name, _)| *name).join("|"));
+    println!("|-|{}|", kernels.iter().map(|_| "-").join("|"));
+
+    let mut size = args.min_map;
+    while size <= args.max_map {
+        let mut map = vec![0; size];
+        let mut hist = vec![0; size];
 
-    if args.program == "cov" {
         // bring two map into cache
         for _ in 0..16 {
             let _ = afl_default_impl::<false, MaxReducer, DifferentIsNovel>(&hist, &map);
         }
 
-        println!("Naive implmentation...");
-        #[cfg(not(feature = "correctness"))]
-        let default_no_novel = measure_rounds(
-            afl_default_impl::<false, MaxReducer, DifferentIsNovel>,
-            &mut hist,
-            &mut map,
-            &mut rand,
-            args.rounds,
-        );
-        let default_novel = measure_rounds(
-            afl_default_impl::<true, MaxReducer, DifferentIsNovel>,
-            &mut hist,
-            &mut map,
-            &mut rand,
-            args.rounds,
-        );
-        println!("std::simd implmentation...");
-        #[cfg(not(feature = "correctness"))]
-        let libafl_simd_no_novel = measure_rounds(
-            afl_nightly_simd::<false>,
-            &mut hist,
-            &mut map,
-            &mut rand,
-            args.rounds,
-        );
-        let libafl_simd_novel = measure_rounds(
-            afl_nightly_simd::<true>,
-            &mut hist,
-            &mut map,
-            &mut rand,
-            args.rounds,
-        );
-        println!("wide128 implmentation...");
-        #[cfg(not(feature = "correctness"))]
-        let wide128_no_novel = measure_rounds(
-            afl_stable_wide_128::<false>,
-            &mut hist,
-            &mut map,
-            &mut rand,
-            args.rounds,
-        );
-        let wide128_novel = measure_rounds(
-            afl_stable_wide_128::<true>,
-            &mut hist,
+        let gbps = kernels
+            .iter()
+            .map(|(name, f)| {
+                let rounds = measure_rounds(*f, &mut hist, &mut map, rng, args.rounds, cache, perf);
+                if let Some(counters) = rounds.counters {
+                    reports.push(format!(
+                        "{} {}: {}",
+                        size,
+                        name,
+                        counters.report(rounds.bytes)
+                    ));
+                }
+                format!("{:.03}", throughput(2 * size, &rounds.tms))
+            })
+            .join("|");
+        println!("|{}|{}|", size, gbps);
+
+        size *= args.step;
+    }
+    for report in reports {
+        println!("{}", report);
+    }
+}
+
+type ResetKernel = fn(&mut [u8]);
+
+fn reset_kernels() -> Vec<(&'static str, ResetKernel)> {
+    let mut kernels: Vec<(&str, ResetKernel)> =
+        vec![("loop", afl_reset_loop), ("fill", afl_reset_fill)];
+    #[cfg(feature = "nightly")]
+    kernels.push(("simd", afl_reset_simd));
+    kernels.extend([
+        ("wide256", afl_reset_wide256 as ResetKernel),
+        ("nontemporal", afl_reset_nontemporal),
+    ]);
+    kernels
+}
+
+/// Time `reset` zeroing a map with `--density` non-zero entries, which is restored before each
+/// round, and check that the map is zero afterwards
+fn measure_reset_rounds(
+    reset: impl Fn(&mut [u8], &mut BlockSummary),
+    map: &mut [u8],
+    dirty: &[u8],
+    summary: &BlockSummary,
+    rounds: usize,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) -> Rounds {
+    let mut tms = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        map.copy_from_slice(dirty);
+        let mut summary = summary.clone();
+        cache.prepare(&mut [map]);
+        let (elp, ()) = time_round(perf, || reset(map, &mut summary));
+        assert!(map.iter().all(|it| *it == 0), "map not reset");
+        tms.push(elp);
+    }
+    Rounds {
+        tms,
+        bytes: map.len() * rounds,
+        counters: perf.take(),
+    }
+}
+
+/// Reset strategies over map sizes, in GB/s of map reset, as a markdown table. `dirty` only
+/// zeroes the blocks with `--density` non-zero entries.
+fn reset(args: &CLI, rng: &mut ThreadRng, cache: &mut CacheControl, perf: &mut PerfCounters) {
+    assert!(args.step > 1, "--step must be at least 2");
+    assert!(args.min_map > 0, "--min-map must be positive");
+
+    let kernels = reset_kernels();
+
+    println!(
+        "|Map|{}|dirty|",
+        kernels.iter().map(|(name, _)| *name).join("|")
+    );
+    println!("|-|{}|-|", kernels.iter().map(|_| "-").join("|"));
+
+    let mut reports = vec![];
+    let mut size = args.min_map;
+    while size <= args.max_map {
+        let mut map = vec![0; size];
+        let mut dirty = vec![0; size];
+        fill_density(&mut dirty, args.density, rng);
+        let mut summary = BlockSummary::new(size);
+        summary.summarize(&dirty);
+
+        let mut gbps = kernels
+            .iter()
+            .map(|(name, f)| {
+                let rounds = measure_reset_rounds(
+                    |map, _| f(map),
+                    &mut map,
+                    &dirty,
+                    &summary,
+                    args.rounds,
+                    cache,
+                    perf,
+                );
+                if let Some(counters) = rounds.counters {
+                    reports.push(format!(
+                        "{} {}: {}",
+                        size,
+                        name,
+                        counters.report(rounds.bytes)
+                    ));
+                }
+                format!("{:.03}", throughput(size, &rounds.tms))
+            })
+            .collect_vec();
+        let rounds = measure_reset_rounds(
+            afl_reset_dirty,
             &mut map,
-            &mut rand,
+            &dirty,
+            &summary,
             args.rounds,
+            cache,
+            perf,
         );
-        println!("wide256 implmentation...");
-        #[cfg(not(feature = "correctness"))]
-        let wide256_no_novel = measure_rounds(
-            afl_stable_wide_256::<false>,
-            &mut hist,
-            &mut map,
-            &mut rand,
-            args.rounds,
+        if let Some(counters) = rounds.counters {
+            reports.push(format!("{} dirty: {}", size, counters.report(rounds.bytes)));
+        }
+        gbps.push(format!("{:.03}", throughput(size, &rounds.tms)));
+        println!("|{}|{}|", size, gbps.join("|"));
+
+        size *= args.step;
+    }
+    for report in reports {
+        println!("{}", report);
+    }
+}
+
+/// Run each kernel alone and then on `--threads` pinned threads with private maps and a shared
+/// `hist`, to see how much the threads slow each other down
+fn threaded(
+    args: &CLI,
+    hist: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    let first_cpu = args.cpu.unwrap_or(0);
+    for (name, f) in cov_kernels() {
+        let single = measure_rounds(f, hist, map, rng, args.rounds, cache, perf);
+        let single_mean = mean_secs(&single.tms);
+
+        let hist = &*hist;
+        let sync = RoundSync {
+            barrier: Barrier::new(args.threads),
+            failed: AtomicBool::new(false),
+        };
+        // set up before spawning, a panic in a thread would leave the others at the barrier
+        let caches = (0..args.threads)
+            .map(|i| CacheControl::new(cache.state, Some(first_cpu + i)))
+            .collect_vec();
+        let results = thread::scope(|s| {
+            let handles = caches
+                .into_iter()
+                .enumerate()
+                .map(|(i, mut cache)| {
+                    let sync = &sync;
+                    s.spawn(move || {
+                        let cpu = first_cpu + i;
+                        // don't panic here, the other threads would wait at the barrier forever
+                        if let Err(e) = pin_to_cpu(cpu) {
+                            println!("Warning: failed to pin thread {} to cpu{}: {}", i, cpu, e);
+                        }
+                        let mut map = vec![0; hist.len()];
+                        measure_shared_rounds(
+                            f,
+                            hist,
+                            &mut map,
+                            &mut rand::rng(),
+                            args.rounds,
+                            &mut cache,
+                            sync,
+                            i == 0,
+                        )
+                    })
+                })
+                .collect_vec();
+            handles.into_iter().map(|h| h.join().unwrap()).collect_vec()
+        });
+        let results = results
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+
+        // the threads start each round together, so a round takes as long as its slowest thread
+        let round_secs = (0..args.rounds)
+            .map(|r| {
+                results
+                    .iter()
+                    .map(|tms| tms[r].to_std().unwrap().as_secs_f64())
+                    .fold(0f64, f64::max)
+            })
+            .sum::<f64>();
+        let aggregate = (args.threads * hist.len() * args.rounds) as f64 / round_secs / 1e9;
+        let slowdowns = results
+            .iter()
+            .map(|tms| format!("{:.03}", mean_secs(tms) / single_mean))
+            .join(", ");
+        println!(
+            "{}: single {:.03} GB/s, {} threads {:.03} GB/s aggregate, slowdown per thread [{}]",
+            name,
+            throughput(hist.len(), &single.tms),
+            args.threads,
+            aggregate,
+            slowdowns
         );
-        let wide256_novel = measure_rounds(
-            afl_stable_wide_256::<true>,
-            &mut hist,
-            &mut map,
-            &mut rand,
-            args.rounds,
+    }
+}
+
+/// Scan one map with `--threads` workers, compared to scanning it on a single thread
+fn parallel(
+    args: &CLI,
+    hist: &mut [u8],
+    map: &mut [u8],
+    rng: &mut ThreadRng,
+    cache: &mut CacheControl,
+    perf: &mut PerfCounters,
+) {
+    let workers = args.threads;
+    // spawned once, outside the measured rounds
+    let (spawn, pool) = time_round(perf, || WorkerPool::new(workers));
+    perf.take();
+    println!(
+        "{} workers spawned in {:.03} ms",
+        workers,
+        spawn.to_std().unwrap().as_secs_f64() * 1e3
+    );
+    let pool = &pool;
+
+    // stops at the first interesting chunk
+    #[cfg(not(feature = "correctness"))]
+    let no_novel = [(
+        "wide256_no_novel",
+        afl_stable_wide_256::<false> as CovKernel,
+        false,
+    )];
+    #[cfg(feature = "correctness")]
+    let no_novel = [];
+    let kernels = cov_kernels()
+        .into_iter()
+        .map(|(name, f)| (name, f, true))
+        .chain(no_novel);
+
+    for (name, f, nv) in kernels {
+        let single = measure_rounds(f, hist, map, rng, args.rounds, cache, perf);
+        let par = if nv {
+            measure_rounds(
+                move |hist: &[u8], map: &[u8]| afl_parallel::<true, _>(hist, map, pool, f),
+                hist,
+                map,
+                rng,
+                args.rounds,
+                cache,
+                perf,
+            )
+        } else {
+            measure_rounds(
+                move |hist: &[u8], map: &[u8]| afl_parallel::<false, _>(hist, map, pool, f),
+                hist,
+                map,
+                rng,
+                args.rounds,
+                cache,
+                perf,
+            )
+        };
+        println!(
+            "{}: single {:.03} GB/s, {} workers {:.03} GB/s",
+            name,
+            throughput(map.len(), &single.tms),
+            workers,
+            throughput(map.len(), &par.tms)
         );
+        for (mode, rounds) in [("single", &single), ("par", &par)] {
+            if let Some(counters) = &rounds.counters {
+                println!("{} {}: {}", name, mode, counters.report(rounds.bytes));
+            }
+        }
+    }
+}
 
-        #[cfg(not(feature = "correctness"))]
-        printout("default_no_novel", default_no_novel);
-        printout("default_novel", default_novel);
-        #[cfg(not(feature = "correctness"))]
-        printout("libafl_simd_no_novel", libafl_simd_no_novel);
-        printout("libafl_simd_novel", libafl_simd_novel);
-        #[cfg(not(feature = "correctness"))]
-        printout("wide128_no_novel", wide128_no_novel);
-        printout("wide128_novel", wide128_novel);
-        #[cfg(not(feature = "correctness"))]
-        printout("wide256_no_novel", wide256_no_novel);
-        printout("wide256_novel", wide256_novel);
-    } else if args.program == "counts" {
-        println!("Naive simplify_counts...");
-        let simplify_naive =
-            measure_counts_rounds(afl_simplify_trace_naive, &mut map, &mut rand, args.rounds);
-        println!("wide128 simplify counts...");
-        let simplify_wide128 =
-            measure_counts_rounds(afl_simplify_trace_wide128, &mut map, &mut rand, args.rounds);
-        println!("wide256 simplify counts...");
-        let simplify_wide256 =
-                measure_counts_rounds(afl_simplify_trace_wide256, &mut map, &mut rand, args.rounds);
-    
-        printout("simplify_naive", simplify_naive);
-        printout("simplify_wide128", simplify_wide128);
-        printout("simplify_wide256", simplify_wide256);
-    } else {
-        panic!("no such bench {}", args.program);
+fn main() {
+    let args = CLI::parse();
+    // before pinning narrows the mask, the `llc` helpers run on the other cpus
+    allowed_cpus();
+    if let Some(cpu) = args.cpu {
+        pin_to_cpu(cpu).expect("failed to pin the benchmark thread");
+    }
+    let preflight = Preflight::run(args.cpu);
+    preflight.print();
+    if let Some(path) = &args.meta {
+        preflight.record(path).expect("failed to write --meta");
+    }
+
+    let mut map = vec![0; args.map];
+    let mut hist = vec![0; args.map];
+    let mut rand = rand::rng();
+    let mut perf = PerfCounters::new(args.perf);
+
+    for state in args.cache.iter().copied() {
+        println!("Cache state {:?}...", state);
+        let mut cache = CacheControl::new(state, args.cpu);
+
+        if args.program == "cov" && args.threads > 1 {
+            threaded(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "cov" {
+            // bring two map into cache
+            for _ in 0..16 {
+                let _ = afl_default_impl::<false, MaxReducer, DifferentIsNovel>(&hist, &map);
+            }
+
+            println!("Naive implmentation...");
+            #[cfg(not(feature = "correctness"))]
+            let default_no_novel = measure_rounds(
+                afl_default_impl::<false, MaxReducer, DifferentIsNovel>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            let default_novel = measure_rounds(
+                afl_default_impl::<true, MaxReducer, DifferentIsNovel>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            #[cfg(feature = "nightly")]
+            println!("std::simd implmentation...");
+            #[cfg(all(feature = "nightly", not(feature = "correctness")))]
+            let libafl_simd_no_novel = measure_rounds(
+                afl_nightly_simd::<false>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            #[cfg(feature = "nightly")]
+            let libafl_simd_novel = measure_rounds(
+                afl_nightly_simd::<true>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            println!("wide128 implmentation...");
+            #[cfg(not(feature = "correctness"))]
+            let wide128_no_novel = measure_rounds(
+                afl_stable_wide_128::<false>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            let wide128_novel = measure_rounds(
+                afl_stable_wide_128::<true>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            println!("wide256 implmentation...");
+            #[cfg(not(feature = "correctness"))]
+            let wide256_no_novel = measure_rounds(
+                afl_stable_wide_256::<false>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            let wide256_novel = measure_rounds(
+                afl_stable_wide_256::<true>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            println!("autovec implmentation...");
+            #[cfg(not(feature = "correctness"))]
+            let autovec_no_novel = measure_rounds(
+                afl_autovec::<false>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            let autovec_novel = measure_rounds(
+                afl_autovec::<true>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            #[cfg(target_arch = "aarch64")]
+            println!("neon implmentation...");
+            #[cfg(all(target_arch = "aarch64", not(feature = "correctness")))]
+            let neon_no_novel = measure_rounds(
+                afl_neon::<false>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            #[cfg(target_arch = "aarch64")]
+            let neon_novel = measure_rounds(
+                afl_neon::<true>,
+                &mut hist,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+
+            #[cfg(not(feature = "correctness"))]
+            printout("default_no_novel", default_no_novel);
+            printout("default_novel", default_novel);
+            #[cfg(all(feature = "nightly", not(feature = "correctness")))]
+            printout("libafl_simd_no_novel", libafl_simd_no_novel);
+            #[cfg(feature = "nightly")]
+            printout("libafl_simd_novel", libafl_simd_novel);
+            #[cfg(not(feature = "correctness"))]
+            printout("wide128_no_novel", wide128_no_novel);
+            printout("wide128_novel", wide128_novel);
+            #[cfg(not(feature = "correctness"))]
+            printout("wide256_no_novel", wide256_no_novel);
+            printout("wide256_novel", wide256_novel);
+            #[cfg(not(feature = "correctness"))]
+            printout("autovec_no_novel", autovec_no_novel);
+            printout("autovec_novel", autovec_novel);
+            #[cfg(all(target_arch = "aarch64", not(feature = "correctness")))]
+            printout("neon_no_novel", neon_no_novel);
+            #[cfg(target_arch = "aarch64")]
+            printout("neon_novel", neon_novel);
+
+            println!("Reused novelty buffers...");
+            for (name, f) in cov_into_kernels() {
+                let rounds = measure_into_rounds(
+                    f,
+                    &mut hist,
+                    &mut map,
+                    &mut rand,
+                    args.rounds,
+                    &mut cache,
+                    &mut perf,
+                );
+                printout(name, rounds);
+            }
+            // room for a quarter of the entries `random_bits` sets, so the later rounds overflow
+            let mut buf = vec![0; (args.rounds / 4).max(1)];
+            let rounds = measure_fixed_rounds(
+                afl_stable_wide_256_into::<true, _>,
+                &mut hist,
+                &mut map,
+                &mut FixedNovelties::new(&mut buf),
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            printout("wide256_novel_fixed", rounds);
+        } else if args.program == "counts" {
+            println!("Naive simplify_counts...");
+            let simplify_naive = measure_counts_rounds(
+                afl_simplify_trace_naive,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            println!("wide128 simplify counts...");
+            let simplify_wide128 = measure_counts_rounds(
+                afl_simplify_trace_wide128,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+            println!("wide256 simplify counts...");
+            let simplify_wide256 = measure_counts_rounds(
+                afl_simplify_trace_wide256,
+                &mut map,
+                &mut rand,
+                args.rounds,
+                &mut cache,
+                &mut perf,
+            );
+
+            let mut simplify_more = vec![];
+            #[cfg(feature = "nightly")]
+            let simd = [
+                (
+                    "simplify_simd128",
+                    afl_simplify_trace_simd128 as fn(&mut [u8]),
+                ),
+                ("simplify_simd256", afl_simplify_trace_simd256),
+                ("simplify_simd512", afl_simplify_trace_simd512),
+            ];
+            #[cfg(not(feature = "nightly"))]
+            let simd = [];
+            let autovec = [(
+                "simplify_autovec",
+                afl_simplify_trace_autovec as fn(&mut [u8]),
+            )];
+            #[cfg(target_arch = "aarch64")]
+            let neon = [("simplify_neon", afl_simplify_trace_neon as fn(&mut [u8]))];
+            #[cfg(not(target_arch = "aarch64"))]
+            let neon = [];
+            for (name, f) in simd.into_iter().chain(autovec).chain(neon) {
+                println!("{} simplify counts...", name);
+                simplify_more.push((
+                    name,
+                    measure_counts_rounds(
+                        f,
+                        &mut map,
+                        &mut rand,
+                        args.rounds,
+                        &mut cache,
+                        &mut perf,
+                    ),
+                ));
+            }
+
+            printout("simplify_naive", simplify_naive);
+            printout("simplify_wide128", simplify_wide128);
+            printout("simplify_wide256", simplify_wide256);
+            for (name, rounds) in simplify_more {
+                printout(name, rounds);
+            }
+        } else if args.program == "density" {
+            density(&args, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "hash" {
+            hash(&args, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "stability" {
+            stability(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "fused" {
+            fused(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "crash" {
+            crash(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "buckets" {
+            buckets(&args, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "counts16" {
+            counts16(&args, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "counts_to" {
+            counts_to(&args, &mut map, &mut hist, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "decode" {
+            decode(&args, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "packed" {
+            packed(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "sparse" {
+            sparse(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "compact" {
+            compact(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "par" {
+            parallel(&args, &mut hist, &mut map, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "reset" {
+            reset(&args, &mut rand, &mut cache, &mut perf);
+        } else if args.program == "sweep" {
+            sweep(&args, &mut rand, &mut cache, &mut perf);
+        } else {
+            panic!("no such bench {}", args.program);
+        }
     }
 }
@@ -0,0 +1,128 @@
+use core::arch::aarch64::*;
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use crate::counts::COUNT_CLASS_LOOKUP;
+use crate::cov::Novelties;
+
+/// Bytes per step of the novelty kernel, four q registers and one cache line
+const BS: usize = 64;
+
+/// Bytes per q register
+const LANES: usize = 16;
+
+#[cfg(feature = "alloc")]
+pub fn afl_neon<const NV: bool>(hist: &[u8], map: &[u8]) -> (bool, Vec<usize>) {
+    let mut novelties = vec![];
+    let interesting = afl_neon_into::<NV, _>(hist, map, &mut novelties);
+    (interesting, novelties)
+}
+
+/// Like `afl_stable_wide_256_into`, a block is novel if `max(map, hist) != hist` in any lane. The
+/// `vceqq_u8` masks of four registers are inverted, ORed and reduced with one `vmaxvq_u8`.
+pub fn afl_neon_into<const NV: bool, N: Novelties>(
+    hist: &[u8],
+    map: &[u8],
+    novelties: &mut N,
+) -> bool {
+    let mut interesting = false;
+    novelties.clear();
+
+    let mut hists = hist[..map.len()].chunks_exact(BS);
+    let mut maps = map.chunks_exact(BS);
+    for (step, (h, m)) in hists.by_ref().zip(maps.by_ref()).enumerate() {
+        let novel = unsafe {
+            let history = vld1q_u8_x4(h.as_ptr());
+            let items = vld1q_u8_x4(m.as_ptr());
+            let same0 = vceqq_u8(vmaxq_u8(items.0, history.0), history.0);
+            let same1 = vceqq_u8(vmaxq_u8(items.1, history.1), history.1);
+            let same2 = vceqq_u8(vmaxq_u8(items.2, history.2), history.2);
+            let same3 = vceqq_u8(vmaxq_u8(items.3, history.3), history.3);
+            let novel = vorrq_u8(
+                vorrq_u8(vmvnq_u8(same0), vmvnq_u8(same1)),
+                vorrq_u8(vmvnq_u8(same2), vmvnq_u8(same3)),
+            );
+            vmaxvq_u8(novel) != 0
+        };
+        if novel {
+            interesting = true;
+            if !NV {
+                return true;
+            }
+            let i = step * BS;
+            for (j, (old, new)) in h.iter().zip(m.iter()).enumerate() {
+                if new > old {
+                    novelties.push(i + j);
+                }
+            }
+        }
+    }
+
+    let body = map.len() - maps.remainder().len();
+    for (j, (old, new)) in hists.remainder().iter().zip(maps.remainder()).enumerate() {
+        if new > old {
+            interesting = true;
+            if !NV {
+                break;
+            }
+            novelties.push(body + j);
+        }
+    }
+
+    interesting
+}
+
+/// `afl_simplify_trace_naive` with `vceqzq_u8` and a bit select
+pub fn afl_simplify_trace_neon(map: &mut [u8]) {
+    let mut chunks = map.chunks_exact_mut(LANES);
+    for chunk in chunks.by_ref() {
+        unsafe {
+            let items = vld1q_u8(chunk.as_ptr());
+            let simplified = vbslq_u8(vceqzq_u8(items), vdupq_n_u8(0x1), vdupq_n_u8(0x80));
+            vst1q_u8(chunk.as_mut_ptr(), simplified);
+        }
+    }
+    for it in chunks.into_remainder() {
+        *it = if *it == 0 { 0x1 } else { 0x80 };
+    }
+}
+
+/// Classify the counts in place through any 256-entry table, such as `BucketScheme::lookup`. A
+/// `vqtbl4q_u8` covers a quarter of the table and gives 0 for indices past it, `vqtbx4q_u8` keeps
+/// the lane instead. Counts are rebased by 64 between the quarters, so each count is in range for
+/// exactly one lookup.
+pub fn classify_counts_lookup_neon(lookup: &[u8; 256], map: &mut [u8]) {
+    let quarter = lookup.as_ptr();
+    let (t0, t1, t2, t3) = unsafe {
+        (
+            vld1q_u8_x4(quarter),
+            vld1q_u8_x4(quarter.add(64)),
+            vld1q_u8_x4(quarter.add(128)),
+            vld1q_u8_x4(quarter.add(192)),
+        )
+    };
+    let mut chunks = map.chunks_exact_mut(LANES);
+    for chunk in chunks.by_ref() {
+        unsafe {
+            let step = vdupq_n_u8(64);
+            let items = vld1q_u8(chunk.as_ptr());
+            let classified = vqtbl4q_u8(t0, items);
+            let items = vsubq_u8(items, step);
+            let classified = vqtbx4q_u8(classified, t1, items);
+            let items = vsubq_u8(items, step);
+            let classified = vqtbx4q_u8(classified, t2, items);
+            let items = vsubq_u8(items, step);
+            let classified = vqtbx4q_u8(classified, t3, items);
+            vst1q_u8(chunk.as_mut_ptr(), classified);
+        }
+    }
+    for it in chunks.into_remainder() {
+        *it = lookup[*it as usize];
+    }
+}
+
+/// `classify_counts_lookup_neon` with AFL's buckets
+pub fn afl_classify_counts_neon(map: &mut [u8]) {
+    classify_counts_lookup_neon(&COUNT_CLASS_LOOKUP, map);
+}
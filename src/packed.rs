@@ -0,0 +1,181 @@
+#[cfg(feature = "nightly")]
+use core::simd::cmp::SimdPartialEq;
+
+use alloc::{vec, vec::Vec};
+use wide::CmpEq;
+
+/// A coverage map with one bit per edge, set if the edge was hit, 8 times smaller than a byte
+/// map. Bit `i % 64` of word `i / 64` is edge `i`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedMap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl PackedMap {
+    /// A map of `len` edges, none hit
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The words to set hits in directly, as instrumentation would. Bits past `len` must stay
+    /// clear.
+    pub fn words_mut(&mut self) -> &mut [u64] {
+        &mut self.words
+    }
+
+    #[inline]
+    pub fn is_hit(&self, idx: usize) -> bool {
+        self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    /// Add the hit edges of `other`, as when merging a map into the history
+    pub fn merge(&mut self, other: &PackedMap) {
+        assert_eq!(self.len, other.len);
+        for (w, o) in self.words.iter_mut().zip(other.words.iter()) {
+            *w |= o;
+        }
+    }
+
+    /// Set the bits of the non-zero entries of the byte `map`, which has `len` entries
+    pub fn pack_naive(&mut self, map: &[u8]) {
+        assert_eq!(self.len, map.len());
+        self.words.fill(0);
+        for (i, it) in map.iter().enumerate() {
+            self.words[i / 64] |= u64::from(*it != 0) << (i % 64);
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    pub fn pack_simd(&mut self, map: &[u8]) {
+        type VectorType = core::simd::u8x64;
+        assert_eq!(self.len, map.len());
+        let zero = VectorType::splat(0);
+        let mut chunks = map.chunks_exact(VectorType::LEN);
+        for (word, chunk) in self.words.iter_mut().zip(chunks.by_ref()) {
+            *word = VectorType::from_slice(chunk).simd_ne(zero).to_bitmask();
+        }
+        self.pack_remainder(chunks.remainder());
+    }
+
+    pub fn pack_wide256(&mut self, map: &[u8]) {
+        // move_mask of the zero compare gives the not-hit entries, 32 at a time
+        type VectorType = wide::u8x32;
+        assert_eq!(self.len, map.len());
+        let mut chunks = map.chunks_exact(64);
+        for (word, chunk) in self.words.iter_mut().zip(chunks.by_ref()) {
+            let (lo, hi) = chunk.split_at(32);
+            let lo = VectorType::new(lo.try_into().unwrap())
+                .cmp_eq(VectorType::ZERO)
+                .move_mask() as u32;
+            let hi = VectorType::new(hi.try_into().unwrap())
+                .cmp_eq(VectorType::ZERO)
+                .move_mask() as u32;
+            *word = !(u64::from(lo) | (u64::from(hi) << 32));
+        }
+        self.pack_remainder(chunks.remainder());
+    }
+
+    /// Pack the entries after the last full word
+    fn pack_remainder(&mut self, rest: &[u8]) {
+        if let Some(last) = self.words.last_mut().filter(|_| !rest.is_empty()) {
+            *last = rest
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, it)| acc | (u64::from(*it != 0) << i));
+        }
+    }
+
+    /// Pack a byte map
+    pub fn from_map(map: &[u8]) -> Self {
+        let mut packed = Self::new(map.len());
+        packed.pack_wide256(map);
+        packed
+    }
+}
+
+/// Edges hit in `map` but not in `hist`, word by word with AND-NOT
+pub fn afl_packed_cov<const NV: bool>(hist: &PackedMap, map: &PackedMap) -> (bool, Vec<usize>) {
+    assert_eq!(hist.len, map.len);
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for (i, (h, m)) in hist.words.iter().zip(map.words.iter()).enumerate() {
+        let mut novel = m & !h;
+        if novel != 0 {
+            interesting = true;
+            if !NV {
+                break;
+            }
+            while novel != 0 {
+                novelties.push(i * 64 + novel.trailing_zeros() as usize);
+                novel &= novel - 1;
+            }
+        }
+    }
+
+    (interesting, novelties)
+}
+
+/// Same as `afl_packed_cov`, four words at a time
+pub fn afl_packed_cov_wide256<const NV: bool>(
+    hist: &PackedMap,
+    map: &PackedMap,
+) -> (bool, Vec<usize>) {
+    type VectorType = wide::u64x4;
+    const BS: usize = 4;
+    assert_eq!(hist.len, map.len);
+    let size = map.words.len();
+    let body = size - size % BS;
+    let mut interesting = false;
+    let mut novelties = vec![];
+
+    for i in (0..body).step_by(BS) {
+        let history = VectorType::new(hist.words[i..i + BS].try_into().unwrap());
+        let items = VectorType::new(map.words[i..i + BS].try_into().unwrap());
+        let novel = items & !history;
+        if novel != VectorType::ZERO {
+            interesting = true;
+            if !NV {
+                return (true, novelties);
+            }
+            for (j, mut word) in novel.to_array().into_iter().enumerate() {
+                while word != 0 {
+                    novelties.push((i + j) * 64 + word.trailing_zeros() as usize);
+                    word &= word - 1;
+                }
+            }
+        }
+    }
+
+    for i in body..size {
+        let mut novel = map.words[i] & !hist.words[i];
+        if novel != 0 {
+            interesting = true;
+            if !NV {
+                break;
+            }
+            while novel != 0 {
+                novelties.push(i * 64 + novel.trailing_zeros() as usize);
+                novel &= novel - 1;
+            }
+        }
+    }
+
+    (interesting, novelties)
+}
@@ -0,0 +1,204 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// Bytes a worker scans between two checks whether another worker already found something
+const BLOCK: usize = 64 * 1024;
+
+/// Chunks after the first start on cache lines of the map, so no two workers share one
+const ALIGN: usize = 64;
+
+/// Scan one chunk in blocks, with novelty indices relative to the chunk
+fn scan_chunk<const NV: bool, K>(
+    hist: &[u8],
+    map: &[u8],
+    kernel: &K,
+    found: &AtomicBool,
+) -> (bool, Vec<usize>)
+where
+    K: Fn(&[u8], &[u8]) -> (bool, Vec<usize>),
+{
+    let mut novelties = vec![];
+    let mut interesting = false;
+
+    for start in (0..map.len()).step_by(BLOCK) {
+        if !NV && found.load(Ordering::Relaxed) {
+            break;
+        }
+        let end = (start + BLOCK).min(map.len());
+        let (block_interesting, block_novelties) = kernel(&hist[start..end], &map[start..end]);
+        if block_interesting {
+            interesting = true;
+            if NV {
+                novelties.extend(block_novelties.into_iter().map(|j| j + start));
+            } else {
+                found.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    }
+
+    (interesting, novelties)
+}
+
+/// Work for one thread of a `WorkerPool`, borrowing from the caller
+type Job<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+struct Worker {
+    jobs: Option<Sender<Job<'static>>>,
+    /// Whether the job finished without panicking
+    done: Receiver<bool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Threads which stay alive across scans, so a scan doesn't pay for spawning them. The calling
+/// thread is one of the workers.
+pub struct WorkerPool {
+    workers: Vec<Worker>,
+}
+
+impl WorkerPool {
+    /// A pool of `workers` threads including the calling one, so `workers - 1` are spawned
+    pub fn new(workers: usize) -> Self {
+        let workers = (1..workers.max(1))
+            .map(|_| {
+                let (jobs, inbox) = mpsc::channel::<Job<'static>>();
+                let (outbox, done) = mpsc::channel();
+                let handle = thread::spawn(move || {
+                    for job in inbox {
+                        let ok = panic::catch_unwind(AssertUnwindSafe(job)).is_ok();
+                        if outbox.send(ok).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Worker {
+                    jobs: Some(jobs),
+                    done,
+                    handle: Some(handle),
+                }
+            })
+            .collect();
+        Self { workers }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.len() + 1
+    }
+
+    /// Run the first job on the calling thread and the others on the pool, at most one job per
+    /// worker, and return once all of them are done
+    fn run(&self, jobs: Vec<Job<'_>>) {
+        assert!(jobs.len() <= self.workers(), "more jobs than workers");
+        let mut jobs = jobs.into_iter();
+        let first = jobs.next();
+        let mut wait = Wait {
+            workers: &self.workers,
+            sent: 0,
+        };
+        for (worker, job) in self.workers.iter().zip(jobs) {
+            // `wait` keeps the caller here until the job is done, so its borrows outlive it
+            let job = unsafe { core::mem::transmute::<Job<'_>, Job<'static>>(job) };
+            worker.jobs.as_ref().unwrap().send(job).unwrap();
+            wait.sent += 1;
+        }
+        if let Some(first) = first {
+            first();
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // closing the channels ends the workers
+        for worker in self.workers.iter_mut() {
+            worker.jobs.take();
+        }
+        for worker in self.workers.iter_mut() {
+            if let Some(handle) = worker.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+/// Waits for the jobs sent to the first `sent` workers, even if the calling thread panics
+struct Wait<'a> {
+    workers: &'a [Worker],
+    sent: usize,
+}
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        // every worker has to be waited for, so no short circuit
+        let mut ok = true;
+        for worker in &self.workers[..self.sent] {
+            ok &= worker.done.recv() == Ok(true);
+        }
+        if !ok && !thread::panicking() {
+            panic!("a worker panicked");
+        }
+    }
+}
+
+/// Split `map` into one chunk per worker of `pool` and scan the chunks concurrently with
+/// `kernel`, which should be the variant of a `cov` kernel with the same `NV`, such as
+/// `afl_stable_wide_256::<NV>`. The novelties are merged in index order. Without `NV`, all
+/// workers stop as soon as any chunk is interesting.
+pub fn afl_parallel<const NV: bool, K>(
+    hist: &[u8],
+    map: &[u8],
+    pool: &WorkerPool,
+    kernel: K,
+) -> (bool, Vec<usize>)
+where
+    K: Fn(&[u8], &[u8]) -> (bool, Vec<usize>) + Sync,
+{
+    let size = map.len();
+    let chunk = size
+        .div_ceil(pool.workers())
+        .next_multiple_of(ALIGN)
+        .max(ALIGN);
+    let found = AtomicBool::new(false);
+
+    // a `Vec<u8>` need not start on a cache line, so the first chunk ends on the one after
+    let head = map.as_ptr().align_offset(ALIGN).min(size);
+    let starts = (size > 0)
+        .then_some(0)
+        .into_iter()
+        .chain((head + chunk..size).step_by(chunk))
+        .collect::<Vec<_>>();
+    let ends = starts.iter().skip(1).copied().chain([size]);
+    let mut results = vec![(false, vec![]); starts.len()];
+    {
+        let found = &found;
+        let kernel = &kernel;
+        let jobs = starts
+            .iter()
+            .zip(ends)
+            .zip(results.iter_mut())
+            .map(|((start, end), result)| {
+                let start = *start;
+                let (hist, map) = (&hist[start..end], &map[start..end]);
+                Box::new(move || *result = scan_chunk::<NV, K>(hist, map, kernel, found)) as Job<'_>
+            })
+            .collect();
+        pool.run(jobs);
+    }
+
+    let mut novelties = vec![];
+    let mut interesting = false;
+    // chunks are in index order already
+    for (start, (chunk_interesting, chunk_novelties)) in starts.into_iter().zip(results) {
+        interesting |= chunk_interesting;
+        if NV {
+            novelties.extend(chunk_novelties.into_iter().map(|j| j + start));
+        }
+    }
+
+    (interesting, novelties)
+}
@@ -0,0 +1,279 @@
+/// Counters we try to open, in group order. The first one leads the group.
+const EVENTS: [&str; 5] = [
+    "cycles",
+    "instructions",
+    "branch-misses",
+    "l1d-misses",
+    "llc-misses",
+];
+
+/// Counter values over some rounds, `None` if the counter is not supported
+#[derive(Clone, Debug)]
+pub struct PerfTotals {
+    pub values: [Option<u64>; EVENTS.len()],
+}
+
+impl PerfTotals {
+    /// IPC and events per KiB of map, given the total bytes scanned
+    pub fn report(&self, bytes: usize) -> String {
+        let kib = bytes as f64 / 1024.0;
+        let ipc = match (self.values[0], self.values[1]) {
+            (Some(cycles), Some(instructions)) if cycles != 0 => {
+                format!("{:.03}", instructions as f64 / cycles as f64)
+            }
+            _ => "n/a".to_string(),
+        };
+        let per_kib = EVENTS
+            .iter()
+            .zip(self.values.iter())
+            .skip(2)
+            .map(|(name, value)| match value {
+                Some(value) => format!("{}/KiB {:.03}", name, *value as f64 / kib),
+                None => format!("{}/KiB n/a", name),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("ipc {}, {}", ipc, per_kib)
+    }
+}
+
+/// A group of hardware counters for the current thread, a no-op if disabled or unavailable
+pub struct PerfCounters {
+    #[cfg(target_os = "linux")]
+    group: Option<sys::Group>,
+}
+
+impl PerfCounters {
+    pub fn new(enabled: bool) -> Self {
+        if !enabled {
+            return Self {
+                #[cfg(target_os = "linux")]
+                group: None,
+            };
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let group = sys::Group::open();
+            if group.is_none() {
+                println!("perf_event_open failed, check /proc/sys/kernel/perf_event_paranoid");
+            }
+            Self { group }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            println!("Hardware counters are only available on Linux");
+            Self {}
+        }
+    }
+
+    #[inline]
+    pub fn start(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(group) = &self.group {
+            group.enable();
+        }
+    }
+
+    #[inline]
+    pub fn stop(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(group) = &self.group {
+            group.disable();
+        }
+    }
+
+    /// Counts since the last `take`
+    pub fn take(&mut self) -> Option<PerfTotals> {
+        #[cfg(target_os = "linux")]
+        {
+            self.group.as_mut().map(|group| group.take())
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::{EVENTS, PerfTotals};
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_TYPE_HW_CACHE: u32 = 3;
+
+    const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+
+    const PERF_COUNT_HW_CACHE_L1D: u64 = 0;
+    const PERF_COUNT_HW_CACHE_LL: u64 = 2;
+    const PERF_COUNT_HW_CACHE_OP_READ: u64 = 0;
+    const PERF_COUNT_HW_CACHE_RESULT_MISS: u64 = 1;
+
+    const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+    const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+    const PERF_FORMAT_GROUP: u64 = 1 << 3;
+
+    const ATTR_DISABLED: u64 = 1 << 0;
+    const ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const ATTR_EXCLUDE_HV: u64 = 1 << 6;
+
+    const PERF_EVENT_IOC_ENABLE: u64 = 0x2400;
+    const PERF_EVENT_IOC_DISABLE: u64 = 0x2401;
+    const PERF_IOC_FLAG_GROUP: u64 = 1;
+    const PERF_FLAG_FD_CLOEXEC: u64 = 1 << 3;
+
+    const PERF_ATTR_SIZE_VER0: u32 = 64;
+
+    /// The first `PERF_ATTR_SIZE_VER0` bytes of `struct perf_event_attr`
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+
+    const fn cache_miss(cache: u64) -> u64 {
+        cache | (PERF_COUNT_HW_CACHE_OP_READ << 8) | (PERF_COUNT_HW_CACHE_RESULT_MISS << 16)
+    }
+
+    const CONFIGS: [(u32, u64); EVENTS.len()] = [
+        (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CPU_CYCLES),
+        (PERF_TYPE_HARDWARE, PERF_COUNT_HW_INSTRUCTIONS),
+        (PERF_TYPE_HARDWARE, PERF_COUNT_HW_BRANCH_MISSES),
+        (PERF_TYPE_HW_CACHE, cache_miss(PERF_COUNT_HW_CACHE_L1D)),
+        (PERF_TYPE_HW_CACHE, cache_miss(PERF_COUNT_HW_CACHE_LL)),
+    ];
+
+    /// `nr`, `time_enabled`, `time_running` and one value per opened counter
+    type Reading = [u64; 3 + EVENTS.len()];
+
+    pub struct Group {
+        fds: Vec<libc::c_int>,
+        /// Index into `EVENTS` for each fd
+        events: Vec<usize>,
+        last: Reading,
+    }
+
+    fn open_event(event: usize, group_fd: libc::c_int) -> Option<libc::c_int> {
+        let (type_, config) = CONFIGS[event];
+        let leader = group_fd == -1;
+        let attr = PerfEventAttr {
+            type_,
+            size: PERF_ATTR_SIZE_VER0,
+            config,
+            read_format: if leader {
+                PERF_FORMAT_GROUP | PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING
+            } else {
+                0
+            },
+            // members follow the leader, which starts disabled
+            flags: if leader { ATTR_DISABLED } else { 0 } | ATTR_EXCLUDE_KERNEL | ATTR_EXCLUDE_HV,
+            ..Default::default()
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                0,
+                -1,
+                group_fd,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            None
+        } else {
+            Some(fd as libc::c_int)
+        }
+    }
+
+    impl Group {
+        /// Open the group, `None` if not even the cycles counter is available
+        pub fn open() -> Option<Self> {
+            let leader = open_event(0, -1)?;
+            let mut fds = vec![leader];
+            let mut events = vec![0];
+            for event in 1..EVENTS.len() {
+                if let Some(fd) = open_event(event, leader) {
+                    fds.push(fd);
+                    events.push(event);
+                }
+            }
+            let mut group = Self {
+                fds,
+                events,
+                last: [0; 3 + EVENTS.len()],
+            };
+            group.last = group.read();
+            Some(group)
+        }
+
+        #[inline]
+        pub fn enable(&self) {
+            unsafe {
+                libc::ioctl(self.fds[0], PERF_EVENT_IOC_ENABLE as _, PERF_IOC_FLAG_GROUP);
+            }
+        }
+
+        #[inline]
+        pub fn disable(&self) {
+            unsafe {
+                libc::ioctl(
+                    self.fds[0],
+                    PERF_EVENT_IOC_DISABLE as _,
+                    PERF_IOC_FLAG_GROUP,
+                );
+            }
+        }
+
+        fn read(&self) -> Reading {
+            let mut buf: Reading = [0; 3 + EVENTS.len()];
+            let ret = unsafe {
+                libc::read(
+                    self.fds[0],
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    size_of::<Reading>(),
+                )
+            };
+            assert!(ret > 0, "failed to read perf counters");
+            buf
+        }
+
+        pub fn take(&mut self) -> PerfTotals {
+            let now = self.read();
+            let enabled = now[1] - self.last[1];
+            let running = now[2] - self.last[2];
+            let mut values = [None; EVENTS.len()];
+            for (i, event) in self.events.iter().enumerate() {
+                let delta = now[3 + i] - self.last[3 + i];
+                // scale up if the kernel had to multiplex the group
+                values[*event] = Some(if running == 0 {
+                    0
+                } else {
+                    (delta as u128 * enabled as u128 / running as u128) as u64
+                });
+            }
+            self.last = now;
+            PerfTotals { values }
+        }
+    }
+
+    impl Drop for Group {
+        fn drop(&mut self) {
+            for fd in self.fds.iter() {
+                unsafe { libc::close(*fd) };
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use chrono::Utc;
+use itertools::Itertools;
+#[cfg(target_os = "linux")]
+use std::{fs, thread, time::Duration};
+
+/// The cpus the process may run on, as of the first call. Call it before pinning, which narrows
+/// the affinity mask down to one cpu.
+pub fn allowed_cpus() -> &'static [usize] {
+    static ALLOWED: OnceLock<Vec<usize>> = OnceLock::new();
+    ALLOWED.get_or_init(|| affinity().unwrap_or_default())
+}
+
+#[cfg(target_os = "linux")]
+fn affinity() -> io::Result<Vec<usize>> {
+    let mut set: libc::cpu_set_t = unsafe { core::mem::zeroed() };
+    let ret = unsafe { libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut set) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((0..libc::CPU_SETSIZE as usize)
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &set) })
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn affinity() -> io::Result<Vec<usize>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// The cpu the current thread runs on right now
+#[cfg(target_os = "linux")]
+pub fn current_cpu() -> Option<usize> {
+    usize::try_from(unsafe { libc::sched_getcpu() }).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn current_cpu() -> Option<usize> {
+    None
+}
+
+/// Pin the current thread to `cpu`
+#[cfg(target_os = "linux")]
+pub fn pin_to_cpu(cpu: usize) -> io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    let mut set: libc::cpu_set_t = unsafe { core::mem::zeroed() };
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+    let ret = unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_to_cpu(_cpu: usize) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Facts about the environment the results were measured in, and what looks off about it
+#[derive(Default)]
+pub struct Preflight {
+    pub facts: Vec<(&'static str, String)>,
+    pub warnings: Vec<String>,
+}
+
+impl Preflight {
+    pub fn run(cpu: Option<usize>) -> Self {
+        let mut pf = Self::default();
+
+        if cfg!(debug_assertions) {
+            pf.facts.push(("build", "debug".to_string()));
+            pf.warnings
+                .push("debug build, numbers are meaningless without --release".to_string());
+        } else {
+            pf.facts.push(("build", "release".to_string()));
+        }
+
+        match cpu {
+            Some(cpu) => pf.facts.push(("cpu", cpu.to_string())),
+            None => {
+                pf.facts.push(("cpu", "unpinned".to_string()));
+                pf.warnings
+                    .push("not pinned to a cpu, consider --cpu".to_string());
+            }
+        }
+
+        pf.facts.push(("started", Utc::now().to_rfc3339()));
+        pf.facts.push(("command", env::args().join(" ")));
+
+        #[cfg(target_os = "linux")]
+        match cpu.or_else(current_cpu) {
+            Some(cpu) => pf.linux(cpu),
+            None => pf.warnings.push(format!(
+                "sched_getcpu failed: {}, skipping the cpu checks",
+                io::Error::last_os_error()
+            )),
+        }
+
+        pf
+    }
+
+    #[cfg(target_os = "linux")]
+    fn linux(&mut self, cpu: usize) {
+        let sysfs = format!("/sys/devices/system/cpu/cpu{}", cpu);
+
+        match read_trimmed(&format!("{}/cpufreq/scaling_governor", sysfs)) {
+            Some(governor) => {
+                if governor != "performance" {
+                    self.warnings.push(format!(
+                        "cpu{} uses the {} frequency governor instead of performance",
+                        cpu, governor
+                    ));
+                }
+                self.facts.push(("governor", governor));
+            }
+            None => self.facts.push(("governor", "unknown".to_string())),
+        }
+
+        let turbo = read_trimmed("/sys/devices/system/cpu/intel_pstate/no_turbo")
+            .map(|no_turbo| no_turbo == "0")
+            .or_else(|| {
+                read_trimmed("/sys/devices/system/cpu/cpufreq/boost").map(|boost| boost == "1")
+            });
+        match turbo {
+            Some(true) => {
+                self.warnings
+                    .push("turbo boost is enabled, frequency depends on load and heat".to_string());
+                self.facts.push(("turbo", "on".to_string()));
+            }
+            Some(false) => self.facts.push(("turbo", "off".to_string())),
+            None => self.facts.push(("turbo", "unknown".to_string())),
+        }
+
+        let siblings = read_trimmed(&format!("{}/topology/thread_siblings_list", sysfs))
+            .map(|list| parse_cpu_list(&list))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|sibling| *sibling != cpu)
+            .collect::<Vec<_>>();
+        let siblings_fact = siblings
+            .iter()
+            .map(|sibling| sibling.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.facts.push((
+            "smt_siblings",
+            if siblings_fact.is_empty() {
+                "none".to_string()
+            } else {
+                siblings_fact
+            },
+        ));
+
+        if !siblings.is_empty() {
+            let before = siblings.iter().map(|s| cpu_times(*s)).collect::<Vec<_>>();
+            thread::sleep(Duration::from_millis(100));
+            for (sibling, before) in siblings.iter().zip(before) {
+                if let (Some((busy0, total0)), Some((busy1, total1))) =
+                    (before, cpu_times(*sibling))
+                {
+                    let load = (busy1 - busy0) as f64 / (total1 - total0).max(1) as f64;
+                    if load > 0.1 {
+                        self.warnings.push(format!(
+                            "smt sibling cpu{} of cpu{} is {:.0}% busy",
+                            sibling,
+                            cpu,
+                            load * 100.0
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// `# key: value` lines for the facts, then the warnings
+    fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.facts
+            .iter()
+            .map(|(key, value)| format!("# {}: {}", key, value))
+            .chain(
+                self.warnings
+                    .iter()
+                    .map(|warning| format!("Warning: {}", warning)),
+            )
+    }
+
+    pub fn print(&self) {
+        for line in self.lines() {
+            println!("{}", line);
+        }
+    }
+
+    /// Write the same lines as `print` to `path`, to keep the environment with the results
+    pub fn record(&self, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        for line in self.lines() {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Parse lists like `1,5-7`
+#[cfg(target_os = "linux")]
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    list.split(',')
+        .filter_map(|range| match range.split_once('-') {
+            Some((lo, hi)) => Some((lo.parse().ok()?..=hi.parse().ok()?).collect::<Vec<_>>()),
+            None => Some(vec![range.parse().ok()?]),
+        })
+        .flatten()
+        .collect()
+}
+
+/// Busy and total jiffies of `cpu` from `/proc/stat`
+#[cfg(target_os = "linux")]
+fn cpu_times(cpu: usize) -> Option<(u64, u64)> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let prefix = format!("cpu{} ", cpu);
+    let line = stat.lines().find(|line| line.starts_with(&prefix))?;
+    let fields = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|f| f.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    let total = fields.iter().sum::<u64>();
+    // idle and iowait
+    let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+    Some((total - idle, total))
+}
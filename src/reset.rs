@@ -0,0 +1,97 @@
+#[cfg(feature = "alloc")]
+use crate::sparse::{BlockSummary, SUMMARY_BLOCK};
+
+/// Zero `map` byte by byte, like `clean_vectors` in the harness
+pub fn afl_reset_loop(map: &mut [u8]) {
+    for it in map.iter_mut() {
+        *it = 0;
+    }
+}
+
+pub fn afl_reset_fill(map: &mut [u8]) {
+    map.fill(0);
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_reset_simd(map: &mut [u8]) {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let zero = VectorType::splat(0);
+    let mut chunks = map.chunks_exact_mut(BS);
+    for chunk in chunks.by_ref() {
+        zero.copy_to_slice(chunk);
+    }
+    chunks.into_remainder().fill(0);
+}
+
+/// Store a `wide::u8x32` zero vector per chunk, unaligned since the chunks need not be 32-byte
+/// aligned
+pub fn afl_reset_wide256(map: &mut [u8]) {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let mut chunks = map.chunks_exact_mut(BS);
+    for chunk in chunks.by_ref() {
+        unsafe {
+            chunk
+                .as_mut_ptr()
+                .cast::<VectorType>()
+                .write_unaligned(VectorType::ZERO)
+        };
+    }
+    chunks.into_remainder().fill(0);
+}
+
+/// Zero `map` with non-temporal stores, which bypass the caches. Pays off once the map does not
+/// fit in the caches anyway, but the next scan of the map then misses.
+#[cfg(target_arch = "x86_64")]
+pub fn afl_reset_nontemporal(map: &mut [u8]) {
+    use core::arch::x86_64::{__m128i, _mm_setzero_si128, _mm_sfence, _mm_stream_si128};
+    // streaming stores need aligned addresses
+    let (head, body, tail) = unsafe { map.align_to_mut::<__m128i>() };
+    head.fill(0);
+    tail.fill(0);
+    unsafe {
+        let zero = _mm_setzero_si128();
+        for it in body.iter_mut() {
+            _mm_stream_si128(it, zero);
+        }
+        // order the streaming stores before later loads of the map
+        _mm_sfence();
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+pub fn afl_reset_nontemporal(map: &mut [u8]) {
+    // stnp stores pairs of registers with a non-temporal hint
+    let mut chunks = map.chunks_exact_mut(16);
+    for chunk in chunks.by_ref() {
+        unsafe {
+            core::arch::asm!(
+                "stnp xzr, xzr, [{}]",
+                in(reg) chunk.as_mut_ptr(),
+                options(nostack, preserves_flags)
+            )
+        };
+    }
+    chunks.into_remainder().fill(0);
+    unsafe { core::arch::asm!("dmb ishst", options(nostack, preserves_flags)) };
+}
+
+/// No non-temporal stores here, plain `fill`
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn afl_reset_nontemporal(map: &mut [u8]) {
+    map.fill(0);
+}
+
+/// Zero only the dirty blocks of `summary` and clear it. Blocks the summary does not mark must
+/// be zero already.
+#[cfg(feature = "alloc")]
+pub fn afl_reset_dirty(map: &mut [u8], summary: &mut BlockSummary) {
+    let size = map.len();
+    for block in summary.dirty_blocks() {
+        let start = block * SUMMARY_BLOCK;
+        let end = (start + SUMMARY_BLOCK).min(size);
+        map[start..end].fill(0);
+    }
+    summary.clear();
+}
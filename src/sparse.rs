@@ -0,0 +1,153 @@
+use alloc::{vec, vec::Vec};
+
+use crate::counts::COUNT_CLASS_LOOKUP;
+use crate::cov::Novelties;
+
+/// Map bytes covered by one summary bit, a cache line
+pub const SUMMARY_BLOCK: usize = 64;
+
+/// One bit per 64-byte block of a map, set if the block may contain non-zero entries. Kernels
+/// taking a summary skip the clean blocks, which are all zero.
+#[derive(Clone)]
+pub struct BlockSummary {
+    words: Vec<u64>,
+}
+
+impl BlockSummary {
+    /// An all-clean summary for a map with `len` entries
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(SUMMARY_BLOCK).div_ceil(64)],
+        }
+    }
+
+    /// Mark the block of `idx` as dirty, as the instrumentation would on each hit
+    #[inline]
+    pub fn mark(&mut self, idx: usize) {
+        let block = idx / SUMMARY_BLOCK;
+        self.words[block / 64] |= 1 << (block % 64);
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Compute the summary of `map` with an OR-reduction of each block
+    pub fn summarize(&mut self, map: &[u8]) {
+        self.clear();
+        let mut blocks = map.chunks_exact(SUMMARY_BLOCK);
+        for (block, chunk) in blocks.by_ref().enumerate() {
+            let reduced = chunk.chunks_exact(size_of::<u64>()).fold(0, |acc, word| {
+                acc | u64::from_ne_bytes(word.try_into().unwrap())
+            });
+            self.words[block / 64] |= u64::from(reduced != 0) << (block % 64);
+        }
+
+        let rest = blocks.remainder();
+        if rest.iter().any(|it| *it != 0) {
+            self.mark(map.len() - rest.len());
+        }
+    }
+
+    #[inline]
+    pub fn is_dirty(&self, block: usize) -> bool {
+        self.words[block / 64] & (1 << (block % 64)) != 0
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Indices of the dirty blocks, in order
+    pub fn dirty_blocks(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            core::iter::from_fn(move || {
+                if word == 0 {
+                    None
+                } else {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(i * 64 + bit)
+                }
+            })
+        })
+    }
+}
+
+pub fn afl_sparse_wide<const NV: bool>(
+    hist: &[u8],
+    map: &[u8],
+    summary: &BlockSummary,
+) -> (bool, Vec<usize>) {
+    let mut novelties = vec![];
+    let interesting = afl_sparse_wide_into::<NV, _>(hist, map, summary, &mut novelties);
+    (interesting, novelties)
+}
+
+/// Like `afl_stable_wide_256_into`, but only scans the dirty blocks of `summary`
+pub fn afl_sparse_wide_into<const NV: bool, N: Novelties>(
+    hist: &[u8],
+    map: &[u8],
+    summary: &BlockSummary,
+    novelties: &mut N,
+) -> bool {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let size = map.len();
+    let mut interesting = false;
+
+    novelties.clear();
+
+    for block in summary.dirty_blocks() {
+        let start = block * SUMMARY_BLOCK;
+        let end = (start + SUMMARY_BLOCK).min(size);
+
+        if end - start < SUMMARY_BLOCK {
+            // the partial last block
+            for j in start..end {
+                if map[j] > hist[j] {
+                    interesting = true;
+                    if !NV {
+                        return true;
+                    }
+                    novelties.push(j);
+                }
+            }
+            continue;
+        }
+
+        for i in (start..end).step_by(BS) {
+            let history = VectorType::new(hist[i..i + BS].try_into().unwrap());
+            let items = VectorType::new(map[i..i + BS].try_into().unwrap());
+
+            if items.max(history) != history {
+                interesting = true;
+                if !NV {
+                    return true;
+                }
+                unsafe {
+                    for j in i..(i + BS) {
+                        if *map.get_unchecked(j) > *hist.get_unchecked(j) {
+                            novelties.push(j);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    interesting
+}
+
+/// Classify the counts of the dirty blocks in place, clean blocks stay zero
+pub fn afl_sparse_classify_counts(map: &mut [u8], summary: &BlockSummary) {
+    let size = map.len();
+    for block in summary.dirty_blocks() {
+        let start = block * SUMMARY_BLOCK;
+        let end = (start + SUMMARY_BLOCK).min(size);
+        for it in map[start..end].iter_mut() {
+            *it = COUNT_CLASS_LOOKUP[*it as usize];
+        }
+    }
+}
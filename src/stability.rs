@@ -0,0 +1,69 @@
+#[cfg(feature = "nightly")]
+use core::simd::{cmp::SimdPartialEq, num::SimdUint};
+
+use wide::CmpEq;
+
+use crate::density::U8_BATCH;
+
+/// Mark the entries where `map` differs from `first` in `var`, AFL's `var_bytes` during
+/// calibration. `var` accumulates over runs, entries are 1 once variable. Returns the number of
+/// variable entries so far.
+pub fn afl_var_bytes_naive(first: &[u8], map: &[u8], var: &mut [u8]) -> usize {
+    let mut count = 0;
+    for ((f, m), v) in first.iter().zip(map.iter()).zip(var.iter_mut()) {
+        *v |= u8::from(f != m);
+        count += *v as usize;
+    }
+    count
+}
+
+#[cfg(feature = "nightly")]
+pub fn afl_var_bytes_simd(first: &[u8], map: &[u8], var: &mut [u8]) -> usize {
+    type VectorType = core::simd::u8x32;
+    const BS: usize = VectorType::LEN;
+    let body = map.len() - map.len() % BS;
+    let zero = VectorType::splat(0);
+    let one = VectorType::splat(1);
+    let mut count = 0;
+
+    for start in (0..body).step_by(BS * U8_BATCH) {
+        let end = (start + BS * U8_BATCH).min(body);
+        let mut acc = zero;
+        for i in (start..end).step_by(BS) {
+            let firsts = VectorType::from_slice(&first[i..i + BS]);
+            let items = VectorType::from_slice(&map[i..i + BS]);
+            let vars =
+                VectorType::from_slice(&var[i..i + BS]) | items.simd_ne(firsts).select(one, zero);
+            vars.copy_to_slice(&mut var[i..i + BS]);
+            acc += vars;
+        }
+        count += acc.cast::<u16>().reduce_sum() as usize;
+    }
+
+    count + afl_var_bytes_naive(&first[body..], &map[body..], &mut var[body..])
+}
+
+pub fn afl_var_bytes_wide256(first: &[u8], map: &[u8], var: &mut [u8]) -> usize {
+    type VectorType = wide::u8x32;
+    const BS: usize = VectorType::LANES as usize;
+    let body = map.len() - map.len() % BS;
+    let one = VectorType::new([1; BS]);
+    let mut count = 0;
+
+    for start in (0..body).step_by(BS * U8_BATCH) {
+        let end = (start + BS * U8_BATCH).min(body);
+        let mut acc = VectorType::ZERO;
+        for i in (start..end).step_by(BS) {
+            let firsts = VectorType::new(first[i..i + BS].try_into().unwrap());
+            let items = VectorType::new(map[i..i + BS].try_into().unwrap());
+            let vars = VectorType::new(var[i..i + BS].try_into().unwrap());
+            // equal lanes are all ones, 1 for the others
+            let vars = vars | ((items.cmp_eq(firsts) & one) ^ one);
+            var[i..i + BS].copy_from_slice(&vars.to_array());
+            acc += vars;
+        }
+        count += acc.to_array().iter().map(|c| *c as usize).sum::<usize>();
+    }
+
+    count + afl_var_bytes_naive(&first[body..], &map[body..], &mut var[body..])
+}